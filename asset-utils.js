@@ -1,6 +1,18 @@
-export async function getAsset(name) {
+async function fetchAsset(name) {
     const res = await fetch(`./${name}`);
-    const blob = await res.blob();
-    const text = await blob.text();
-    return text;
+    if (!res.ok) {
+        throw new Error(`Failed to fetch ${name}: ${res.status} ${res.statusText}`);
+    }
+    return res;
+}
+
+export async function getAsset(name) {
+    const res = await fetchAsset(name);
+    return await res.text();
+}
+
+export async function getAssetBytes(name) {
+    const res = await fetchAsset(name);
+    const buffer = await res.arrayBuffer();
+    return new Uint8Array(buffer);
 }
\ No newline at end of file
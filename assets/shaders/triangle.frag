@@ -1,8 +1,11 @@
 #version 300 es
     
 precision highp float;
+
+uniform vec4 u_color;
+
 out vec4 outColor;
 
 void main() {
-    outColor = vec4(1, 1, 1, 1);
-}
\ No newline at end of file
+    outColor = u_color;
+}
@@ -1,7 +1,11 @@
 #version 300 es
 
-in vec4 position;
+layout(location = 0) in vec4 position;
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
 
 void main() {
-    gl_Position = position;
-}
\ No newline at end of file
+    gl_Position = u_projection * u_view * u_model * position;
+}
@@ -0,0 +1,37 @@
+use std::rc::Rc;
+
+use crate::renderer::render_backend::RenderBackend;
+use crate::renderer::texture::{Texture2D, TextureSettings};
+
+// Anything that can be built from the bytes of a fetched file.
+// `Settings` are passed along from `AssetManager::load_with`, `load` uses the default ones.
+pub trait Asset: Sized + 'static {
+    type Settings: Clone + Default + 'static;
+
+    fn from_bytes(bytes: Vec<u8>, settings: &Self::Settings, backend: &Rc<dyn RenderBackend>) -> Result<Self, String>;
+}
+
+impl Asset for Vec<u8> {
+    type Settings = ();
+
+    fn from_bytes(bytes: Vec<u8>, _settings: &(), _backend: &Rc<dyn RenderBackend>) -> Result<Self, String> {
+        Ok(bytes)
+    }
+}
+
+// Text files, like shader sources
+impl Asset for String {
+    type Settings = ();
+
+    fn from_bytes(bytes: Vec<u8>, _settings: &(), _backend: &Rc<dyn RenderBackend>) -> Result<Self, String> {
+        String::from_utf8(bytes).map_err(|err| format!("Not valid UTF-8: {}", err))
+    }
+}
+
+impl Asset for Texture2D {
+    type Settings = TextureSettings;
+
+    fn from_bytes(bytes: Vec<u8>, settings: &TextureSettings, backend: &Rc<dyn RenderBackend>) -> Result<Self, String> {
+        Texture2D::from_image_bytes(backend, &bytes, *settings)
+    }
+}
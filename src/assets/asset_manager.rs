@@ -0,0 +1,157 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::Promise;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+
+use crate::assets::asset::Asset;
+use crate::assets::fetch::fetch_bytes;
+use crate::assets::handle::{AssetStorage, Handle, LoadState};
+use crate::console;
+use crate::renderer::render_backend::RenderBackend;
+
+// Loads assets in the background and caches them by path.
+// Loading a path that is already loaded or loading returns a handle to the same asset,
+// it's released once the last handle to it is dropped.
+pub struct AssetManager {
+    backend: Rc<dyn RenderBackend>,
+    storage: Rc<RefCell<AssetStorage>>,
+}
+
+#[allow(dead_code)]
+impl AssetManager {
+    pub fn new(backend: Rc<dyn RenderBackend>) -> Self {
+        Self {
+            backend,
+            storage: Rc::new(RefCell::new(AssetStorage::new())),
+        }
+    }
+
+    pub fn load<T: Asset>(&self, path: &str) -> Handle<T> {
+        self.load_with(path, T::Settings::default())
+    }
+
+    // The settings are only used if the asset isn't cached yet
+    pub fn load_with<T: Asset>(&self, path: &str, settings: T::Settings) -> Handle<T> {
+        let mut storage = self.storage.borrow_mut();
+        if let Some(id) = storage.find(path, TypeId::of::<T>()) {
+            storage.retain(id);
+            return Handle::new(id, self.storage.clone());
+        }
+
+        let id = storage.insert(path, TypeId::of::<T>());
+        drop(storage);
+
+        let storage = self.storage.clone();
+        let backend = self.backend.clone();
+        let path = path.to_string();
+        let promise = future_to_promise(async move {
+            let result = fetch_bytes(&path)
+                .await
+                .and_then(|bytes| T::from_bytes(bytes, &settings, &backend));
+            if let Err(err) = &result {
+                console::error!("Failed to load {}: {}", path, err);
+            }
+
+            let result = result.map(|asset| Rc::new(asset) as Rc<dyn Any>);
+            let unused = storage.borrow_mut().finish(id, result);
+            drop(unused);
+
+            Ok(JsValue::UNDEFINED)
+        });
+        self.storage.borrow_mut().set_loading(id, promise);
+
+        Handle::new(id, self.storage.clone())
+    }
+
+    // Number of cached assets, including the ones still loading
+    pub fn get_asset_count(&self) -> usize {
+        self.storage.borrow().entries.len()
+    }
+
+    pub fn get_loading_count(&self) -> usize {
+        self.storage
+            .borrow()
+            .entries
+            .values()
+            .filter(|entry| entry.state == LoadState::Loading)
+            .count()
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.get_loading_count() > 0
+    }
+
+    // Share of the cached assets that finished loading, successfully or not, from 0 to 1
+    pub fn get_progress(&self) -> f32 {
+        let total = self.get_asset_count();
+        if total == 0 {
+            return 1.0;
+        }
+        (total - self.get_loading_count()) as f32 / total as f32
+    }
+
+    // Path and reason of every cached asset that failed to load
+    pub fn get_errors(&self) -> Vec<(String, String)> {
+        self.storage
+            .borrow()
+            .entries
+            .values()
+            .filter_map(|entry| match &entry.state {
+                LoadState::Failed(err) => Some((entry.path.clone(), err.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Waits for a single asset, whatever else is loading is left alone
+    pub async fn wait<T: Asset>(&self, handle: &Handle<T>) -> Result<Rc<T>, String> {
+        let loading = self
+            .storage
+            .borrow()
+            .entries
+            .get(&handle.get_id())
+            .and_then(|entry| entry.loading.clone());
+        if let Some(loading) = loading {
+            // loads always resolve, failures are kept with their asset
+            let _ = JsFuture::from(loading).await;
+        }
+
+        handle.get().ok_or_else(|| {
+            let err = handle.get_error().unwrap_or_else(|| String::from("not loaded"));
+            format!("Failed to load {}: {}", handle.get_path(), err)
+        })
+    }
+
+    // Waits until every load started so far is done, returns the errors of the loads it waited for.
+    // Assets that failed before are not reported again, see `get_errors` for those.
+    pub async fn finish_loading(&self) -> Result<(), String> {
+        let mut waited = Vec::new();
+        loop {
+            let loading = self.storage.borrow().get_loading();
+            if loading.is_empty() {
+                break;
+            }
+            let promises: js_sys::Array = loading.iter().map(|(_, promise)| promise).collect();
+            waited.extend(loading.iter().map(|(id, _)| *id));
+            // loads always resolve, failures are kept with their asset
+            let _ = JsFuture::from(Promise::all(&promises)).await;
+        }
+
+        let storage = self.storage.borrow();
+        let errors: Vec<String> = waited
+            .iter()
+            .filter_map(|id| storage.entries.get(id))
+            .filter_map(|entry| match &entry.state {
+                LoadState::Failed(err) => Some(format!("Failed to load {}: {}", entry.path, err)),
+                _ => None,
+            })
+            .collect();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(errors.join("\n"))
+    }
+}
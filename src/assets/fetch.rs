@@ -0,0 +1,21 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen(raw_module="/asset-utils.js")]
+extern "C" {
+    #[wasm_bindgen(catch, js_name=getAssetBytes)]
+    async fn get_asset_bytes(name: &str) -> Result<JsValue, JsValue>;
+}
+
+// Fetches an asset relative to the page and returns its raw bytes
+pub async fn fetch_bytes(path: &str) -> Result<Vec<u8>, String> {
+    let bytes = get_asset_bytes(path).await.map_err(error_message)?;
+    Ok(js_sys::Uint8Array::new(&bytes).to_vec())
+}
+
+fn error_message(err: JsValue) -> String {
+    match err.dyn_ref::<js_sys::Error>() {
+        Some(err) => String::from(err.message()),
+        None => err.as_string().unwrap_or_else(|| format!("{:?}", err)),
+    }
+}
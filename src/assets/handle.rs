@@ -0,0 +1,246 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use js_sys::Promise;
+
+use crate::assets::asset::Asset;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    // Fetching or parsing failed, with the reason
+    Failed(String),
+}
+
+pub(super) struct AssetEntry {
+    pub path: String,
+    pub state: LoadState,
+    pub asset: Option<Rc<dyn Any>>,
+    // Resolves once the load is done, while the asset is loading
+    pub loading: Option<Promise>,
+    ref_count: usize,
+}
+
+// Everything the manager has loaded or is loading, shared with the handles
+pub(super) struct AssetStorage {
+    next_id: u32,
+    pub entries: HashMap<u32, AssetEntry>,
+    // The same path can be loaded as different asset types
+    ids: HashMap<(String, TypeId), u32>,
+}
+
+impl AssetStorage {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            entries: HashMap::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn find(&self, path: &str, type_id: TypeId) -> Option<u32> {
+        self.ids.get(&(path.to_string(), type_id)).copied()
+    }
+
+    // Adds an entry in the loading state, referenced once
+    pub fn insert(&mut self, path: &str, type_id: TypeId) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.entries.insert(id, AssetEntry {
+            path: path.to_string(),
+            state: LoadState::Loading,
+            asset: None,
+            loading: None,
+            ref_count: 1,
+        });
+        self.ids.insert((path.to_string(), type_id), id);
+        id
+    }
+
+    pub fn set_loading(&mut self, id: u32, promise: Promise) {
+        if let Some(entry) = self.entries.get_mut(&id) {
+            if entry.state == LoadState::Loading {
+                entry.loading = Some(promise);
+            }
+        }
+    }
+
+    // Ids and promises of every asset that is still loading
+    pub fn get_loading(&self) -> Vec<(u32, Promise)> {
+        self.entries
+            .iter()
+            .filter_map(|(id, entry)| Some((*id, entry.loading.clone()?)))
+            .collect()
+    }
+
+    pub fn retain(&mut self, id: u32) {
+        if let Some(entry) = self.entries.get_mut(&id) {
+            entry.ref_count += 1;
+        }
+    }
+
+    // Returns the entry once its last handle is gone, so it's dropped outside of the borrow
+    pub fn release(&mut self, id: u32) -> Option<AssetEntry> {
+        let entry = self.entries.get_mut(&id)?;
+        entry.ref_count -= 1;
+        if entry.ref_count > 0 {
+            return None;
+        }
+
+        self.ids.retain(|_, entry_id| *entry_id != id);
+        self.entries.remove(&id)
+    }
+
+    // Stores the result of a load. Loads nobody holds a handle to anymore are handed back.
+    pub fn finish(&mut self, id: u32, result: Result<Rc<dyn Any>, String>) -> Option<Rc<dyn Any>> {
+        let Some(entry) = self.entries.get_mut(&id) else {
+            return result.ok();
+        };
+
+        entry.loading = None;
+        match result {
+            Ok(asset) => {
+                entry.state = LoadState::Loaded;
+                entry.asset = Some(asset);
+            }
+            Err(err) => entry.state = LoadState::Failed(err),
+        }
+        None
+    }
+}
+
+// A reference counted reference to an asset of the `AssetManager`.
+// The asset stays cached while any handle to it is alive.
+pub struct Handle<T: Asset> {
+    id: u32,
+    storage: Rc<RefCell<AssetStorage>>,
+    asset_type: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: Asset> Handle<T> {
+    // Takes over a reference that was already counted
+    pub(super) fn new(id: u32, storage: Rc<RefCell<AssetStorage>>) -> Self {
+        Self {
+            id,
+            storage,
+            asset_type: PhantomData,
+        }
+    }
+
+    pub(super) fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    // `None` until the asset is loaded, or if it failed to load
+    pub fn get(&self) -> Option<Rc<T>> {
+        let asset = self.storage.borrow().entries.get(&self.id)?.asset.clone()?;
+        asset.downcast::<T>().ok()
+    }
+
+    pub fn get_state(&self) -> LoadState {
+        self.storage
+            .borrow()
+            .entries
+            .get(&self.id)
+            .map(|entry| entry.state.clone())
+            .unwrap_or(LoadState::Loading)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.get_state() == LoadState::Loaded
+    }
+
+    pub fn get_error(&self) -> Option<String> {
+        match self.get_state() {
+            LoadState::Failed(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    pub fn get_path(&self) -> String {
+        self.storage
+            .borrow()
+            .entries
+            .get(&self.id)
+            .map(|entry| entry.path.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl<T: Asset> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        self.storage.borrow_mut().retain(self.id);
+        Self::new(self.id, self.storage.clone())
+    }
+}
+
+impl<T: Asset> Drop for Handle<T> {
+    fn drop(&mut self) {
+        let entry = self.storage.borrow_mut().release(self.id);
+        // the asset may hold handles of its own
+        drop(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(id: u32, storage: &Rc<RefCell<AssetStorage>>) -> Handle<Vec<u8>> {
+        Handle::new(id, storage.clone())
+    }
+
+    #[test]
+    fn same_path_and_type_finds_the_same_entry() {
+        let mut storage = AssetStorage::new();
+        let id = storage.insert("data.bin", TypeId::of::<Vec<u8>>());
+
+        assert_eq!(storage.find("data.bin", TypeId::of::<Vec<u8>>()), Some(id));
+        assert_eq!(storage.find("data.bin", TypeId::of::<String>()), None);
+        assert_eq!(storage.find("other.bin", TypeId::of::<Vec<u8>>()), None);
+    }
+
+    #[test]
+    fn entries_are_evicted_with_their_last_handle() {
+        let storage = Rc::new(RefCell::new(AssetStorage::new()));
+        let id = storage.borrow_mut().insert("data.bin", TypeId::of::<Vec<u8>>());
+        let first = handle(id, &storage);
+        let second = first.clone();
+
+        drop(first);
+        assert!(storage.borrow().entries.contains_key(&id));
+
+        drop(second);
+        assert!(storage.borrow().entries.is_empty());
+        assert_eq!(storage.borrow().find("data.bin", TypeId::of::<Vec<u8>>()), None);
+    }
+
+    #[test]
+    fn finished_loads_are_stored_while_referenced() {
+        let storage = Rc::new(RefCell::new(AssetStorage::new()));
+        let id = storage.borrow_mut().insert("data.bin", TypeId::of::<Vec<u8>>());
+        let data = handle(id, &storage);
+
+        let unused = storage.borrow_mut().finish(id, Ok(Rc::new(vec![1u8, 2, 3])));
+        assert!(unused.is_none());
+        assert!(data.is_loaded());
+        assert_eq!(*data.get().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn loads_finishing_after_the_last_handle_are_handed_back() {
+        let storage = Rc::new(RefCell::new(AssetStorage::new()));
+        let id = storage.borrow_mut().insert("data.bin", TypeId::of::<Vec<u8>>());
+        drop(handle(id, &storage));
+
+        let unused = storage.borrow_mut().finish(id, Ok(Rc::new(vec![1u8, 2, 3])));
+        assert!(unused.is_some());
+        assert!(storage.borrow().entries.is_empty());
+    }
+}
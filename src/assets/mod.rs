@@ -0,0 +1,9 @@
+mod asset;
+mod asset_manager;
+mod fetch;
+mod handle;
+pub mod model;
+pub mod obj;
+
+pub use asset_manager::AssetManager;
+pub use handle::Handle;
\ No newline at end of file
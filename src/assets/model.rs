@@ -0,0 +1,326 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::assets::obj::{parse_mtl, parse_obj, MtlMaterial, ObjModel};
+use crate::assets::{AssetManager, Handle};
+use crate::console;
+use crate::drawables::mesh_renderer::MeshRenderer;
+use crate::objects::game_object::GameObject;
+use crate::renderer::material::{BlendMode, Material};
+use crate::renderer::mesh::Mesh;
+use crate::renderer::shader_program::ShaderProgram;
+use crate::renderer::texture::Texture2D;
+
+struct ModelPart {
+    mesh: Rc<RefCell<Mesh>>,
+    material: Rc<RefCell<Material>>,
+}
+
+struct ModelNode {
+    name: String,
+    parts: Vec<ModelPart>,
+    children: Vec<ModelNode>,
+}
+
+// Meshes and materials of an imported file, arranged like its objects and groups.
+// Every spawned copy draws the same meshes and materials, so they're uploaded once.
+pub struct Model {
+    root: ModelNode,
+    materials: HashMap<String, Rc<RefCell<Material>>>,
+}
+
+#[allow(dead_code)]
+impl Model {
+    // Objects become children of the model, groups children of their object.
+    // Parts using a material missing from `materials` are drawn with `default_material`.
+    pub fn from_obj(
+        name: &str,
+        obj: &ObjModel,
+        materials: HashMap<String, Rc<RefCell<Material>>>,
+        default_material: Rc<RefCell<Material>>,
+    ) -> Self {
+        let mut root = ModelNode {
+            name: name.to_string(),
+            parts: Vec::new(),
+            children: Vec::new(),
+        };
+
+        for object in &obj.objects {
+            let mut object_node = ModelNode {
+                name: object.name.clone(),
+                parts: Vec::new(),
+                children: Vec::new(),
+            };
+
+            for group in &object.groups {
+                let parts = group
+                    .parts
+                    .iter()
+                    .map(|part| {
+                        let material = part
+                            .material
+                            .as_ref()
+                            .and_then(|material| materials.get(material))
+                            .unwrap_or(&default_material);
+                        ModelPart {
+                            mesh: Rc::new(RefCell::new(part.to_mesh())),
+                            material: material.clone(),
+                        }
+                    })
+                    .collect();
+
+                // faces outside of any group belong to the object itself
+                if group.name.is_empty() {
+                    object_node.parts.extend(parts);
+                } else {
+                    object_node.children.push(ModelNode {
+                        name: group.name.clone(),
+                        parts,
+                        children: Vec::new(),
+                    });
+                }
+            }
+
+            // the same goes for faces outside of any object
+            if object.name.is_empty() {
+                root.parts.extend(object_node.parts);
+                root.children.extend(object_node.children);
+            } else {
+                root.children.push(object_node);
+            }
+        }
+
+        Self { root, materials }
+    }
+
+    // Loads an OBJ file, its MTL files and their diffuse textures.
+    // Missing materials and textures are reported, the model is still loaded without them.
+    pub async fn load_obj(assets: &AssetManager, path: &str, program: Rc<ShaderProgram>) -> Result<Self, String> {
+        let source = assets.load::<String>(path);
+        let source = assets.wait(&source).await?;
+        let obj = parse_obj(&source).map_err(|err| format!("{}: {}", path, err))?;
+
+        let libraries: Vec<(String, Handle<String>)> = obj
+            .material_libraries
+            .iter()
+            .map(|library| {
+                let library = resolve_path(path, library);
+                let handle = assets.load::<String>(&library);
+                (library, handle)
+            })
+            .collect();
+
+        let mut mtl_materials: Vec<(String, MtlMaterial)> = Vec::new();
+        for (library, handle) in &libraries {
+            let parsed = match assets.wait(handle).await {
+                Ok(source) => parse_mtl(&source).map_err(|err| format!("{}: {}", library, err)),
+                Err(err) => Err(err),
+            };
+            match parsed {
+                Ok(parsed) => mtl_materials.extend(parsed.into_iter().map(|material| (library.clone(), material))),
+                Err(err) => console::error!("Skipping material library {}", err),
+            }
+        }
+
+        // all textures are fetched at the same time
+        let textures: Vec<Option<Handle<Texture2D>>> = mtl_materials
+            .iter()
+            .map(|(library, material)| {
+                let texture = material.diffuse_texture.as_ref()?;
+                Some(assets.load::<Texture2D>(&resolve_path(library, texture)))
+            })
+            .collect();
+
+        let mut materials = HashMap::new();
+        for ((_, material), texture) in mtl_materials.iter().zip(textures.iter()) {
+            let texture = match texture {
+                Some(texture) => match assets.wait(texture).await {
+                    Ok(texture) => Some(texture),
+                    Err(err) => {
+                        console::error!("Material {} is drawn without its texture: {}", material.name, err);
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            let created = Self::create_material(material, program.clone(), texture);
+            materials.insert(material.name.clone(), Rc::new(RefCell::new(created)));
+        }
+
+        let mut default_material = Material::new(program);
+        default_material.set_vec4("u_color", glm::vec4(1.0, 1.0, 1.0, 1.0));
+
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let name = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+        Ok(Self::from_obj(name, &obj, materials, Rc::new(RefCell::new(default_material))))
+    }
+
+    // Sets `u_color` from the diffuse color and opacity and `u_texture` to the diffuse texture
+    pub fn create_material(mtl: &MtlMaterial, program: Rc<ShaderProgram>, texture: Option<Rc<Texture2D>>) -> Material {
+        let mut material = Material::new(program);
+        let color = mtl.diffuse_color;
+        material.set_vec4("u_color", glm::vec4(color.x, color.y, color.z, mtl.opacity));
+        if let Some(texture) = texture {
+            material.set_texture("u_texture", texture);
+        }
+        if mtl.opacity < 1.0 {
+            material.set_blend_mode(BlendMode::Alpha);
+            material.set_depth_write(false);
+        }
+        material
+    }
+
+    pub fn get_material(&self, name: &str) -> Option<Rc<RefCell<Material>>> {
+        self.materials.get(name).cloned()
+    }
+
+    // Creates the object hierarchy of the model as a child of `parent`
+    pub fn spawn(&self, parent: &Rc<RefCell<GameObject>>) -> Rc<RefCell<GameObject>> {
+        spawn_node(&self.root, parent)
+    }
+}
+
+fn spawn_node(node: &ModelNode, parent: &Rc<RefCell<GameObject>>) -> Rc<RefCell<GameObject>> {
+    let object = parent.borrow_mut().add_child(GameObject::new_named(&node.name));
+    for part in &node.parts {
+        object
+            .borrow_mut()
+            .add_component(MeshRenderer::from_shared(part.mesh.clone(), part.material.clone()));
+    }
+    for child in &node.children {
+        spawn_node(child, &object);
+    }
+    object
+}
+
+// Paths in OBJ and MTL files are relative to the file referring to them
+fn resolve_path(base: &str, relative: &str) -> String {
+    match base.rsplit_once('/') {
+        Some((directory, _)) => format!("{}/{}", directory, relative),
+        None => relative.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::app_state::AppState;
+    use crate::renderer::material::UniformValue;
+    use crate::renderer::recording_backend::RecordingBackend;
+    use crate::renderer::render_backend::RenderBackend;
+
+    const VERT_SOURCE: &str = "#version 300 es
+layout(location = 0) in vec3 position;
+void main() {
+    gl_Position = vec4(position, 1.0);
+}";
+
+    const FRAG_SOURCE: &str = "#version 300 es
+precision highp float;
+uniform vec4 u_color;
+out vec4 outColor;
+void main() {
+    outColor = u_color;
+}";
+
+    const OBJ: &str = "mtllib crate.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3
+o Box
+usemtl red
+f 1 2 3
+usemtl glass
+f 1 3 4
+g Lid
+usemtl red
+f 1 2 4
+";
+
+    const MTL: &str = "newmtl red
+Kd 1 0 0
+newmtl glass
+Kd 0 0 1
+Tr 0.75
+";
+
+    fn renderers(object: &Rc<RefCell<GameObject>>) -> Vec<Rc<RefCell<Material>>> {
+        object
+            .borrow()
+            .get_components::<MeshRenderer>()
+            .iter()
+            .map(|renderer| renderer.borrow().get_material())
+            .collect()
+    }
+
+    #[test]
+    fn spawned_objects_follow_the_obj_hierarchy() {
+        let backend: Rc<dyn RenderBackend> = Rc::new(RecordingBackend::new(640, 480));
+        let program = Rc::new(ShaderProgram::new(&backend, VERT_SOURCE, FRAG_SOURCE).unwrap());
+        let mut state = AppState::new(backend, 0.0);
+
+        let obj = parse_obj(OBJ).unwrap();
+        let materials = parse_mtl(MTL)
+            .unwrap()
+            .iter()
+            .map(|mtl| {
+                let material = Model::create_material(mtl, program.clone(), None);
+                (mtl.name.clone(), Rc::new(RefCell::new(material)))
+            })
+            .collect();
+        let default_material = Rc::new(RefCell::new(Material::new(program)));
+        let model = Model::from_obj("crate", &obj, materials, default_material.clone());
+
+        let parent = state.add_object_empy();
+        let spawned = model.spawn(&parent);
+        assert_eq!(spawned.borrow().get_name(), "crate");
+
+        // faces outside of any object use the default material, as they have none
+        let loose = renderers(&spawned);
+        assert_eq!(loose.len(), 1);
+        assert!(Rc::ptr_eq(&loose[0], &default_material));
+
+        let red = model.get_material("red").unwrap();
+        let glass = model.get_material("glass").unwrap();
+        let crate_box = spawned.borrow().find_child("Box").unwrap();
+        let box_materials = renderers(&crate_box);
+        assert_eq!(box_materials.len(), 2);
+        assert!(Rc::ptr_eq(&box_materials[0], &red));
+        assert!(Rc::ptr_eq(&box_materials[1], &glass));
+
+        let lid = spawned.borrow().find_by_path("Box/Lid").unwrap();
+        let lid_materials = renderers(&lid);
+        assert_eq!(lid_materials.len(), 1);
+        assert!(Rc::ptr_eq(&lid_materials[0], &red));
+
+        assert_eq!(red.borrow().get_blend_mode(), BlendMode::Opaque);
+        assert_eq!(glass.borrow().get_blend_mode(), BlendMode::Alpha);
+        assert_eq!(
+            glass.borrow().get_uniform("u_color"),
+            Some(&UniformValue::Vec4(glm::vec4(0.0, 0.0, 1.0, 0.25)))
+        );
+    }
+
+    #[test]
+    fn spawned_copies_share_meshes() {
+        let backend: Rc<dyn RenderBackend> = Rc::new(RecordingBackend::new(640, 480));
+        let program = Rc::new(ShaderProgram::new(&backend, VERT_SOURCE, FRAG_SOURCE).unwrap());
+        let mut state = AppState::new(backend, 0.0);
+
+        let obj = parse_obj(OBJ).unwrap();
+        let model = Model::from_obj("crate", &obj, HashMap::new(), Rc::new(RefCell::new(Material::new(program))));
+        let parent = state.add_object_empy();
+        let first = model.spawn(&parent);
+        let second = model.spawn(&parent);
+
+        let mesh = |object: &Rc<RefCell<GameObject>>| {
+            object.borrow().get_components::<MeshRenderer>()[0].borrow().get_mesh()
+        };
+        assert!(Rc::ptr_eq(&mesh(&first), &mesh(&second)));
+        assert_eq!(parent.borrow().find_child("crate").map(|object| Rc::ptr_eq(&object, &first)), Some(true));
+    }
+}
@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use glm::{Vec2, Vec3};
+
+use crate::renderer::mesh::{Mesh, VertexLayout};
+
+// Wavefront OBJ and MTL parsing. Nothing here touches the GPU,
+// the parsed model is turned into meshes and game objects by `model`.
+
+// Where and why parsing failed, lines are counted from 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl From<ParseError> for String {
+    fn from(err: ParseError) -> Self {
+        err.to_string()
+    }
+}
+
+// Faces of one group drawn with the same material, as an indexed triangle list.
+// Vertices are interleaved as position (location 0), normal (1) and UV (2).
+pub struct ObjPart {
+    pub material: Option<String>,
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl ObjPart {
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .with_attribute(0, 3)
+            .with_attribute(1, 3)
+            .with_attribute(2, 2)
+    }
+
+    pub fn to_mesh(&self) -> Mesh {
+        Mesh::new(self.vertices.clone(), Some(self.indices.clone()), Self::layout())
+    }
+}
+
+// A `g` statement, faces before any group end up in one without a name
+pub struct ObjGroup {
+    pub name: String,
+    pub parts: Vec<ObjPart>,
+}
+
+// An `o` statement, faces before any object end up in one without a name
+pub struct ObjObject {
+    pub name: String,
+    pub groups: Vec<ObjGroup>,
+}
+
+pub struct ObjModel {
+    pub objects: Vec<ObjObject>,
+    // Paths from `mtllib`, relative to the OBJ file
+    pub material_libraries: Vec<String>,
+}
+
+// A material from an MTL file, only what the engine can draw is kept
+#[derive(Clone, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub name: String,
+    pub diffuse_color: Vec3,
+    pub opacity: f32,
+    // Path from `map_Kd`, relative to the MTL file
+    pub diffuse_texture: Option<String>,
+}
+
+const FLOATS_PER_VERTEX: usize = 8;
+
+// Position, UV and normal indices of a face corner
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+struct PartBuilder {
+    part: ObjPart,
+    // corners already emitted, so shared corners are emitted once
+    emitted: HashMap<VertexKey, u32>,
+}
+
+struct GroupBuilder {
+    name: String,
+    parts: Vec<PartBuilder>,
+}
+
+struct ObjectBuilder {
+    name: String,
+    groups: Vec<GroupBuilder>,
+}
+
+pub fn parse_obj(source: &str) -> Result<ObjModel, ParseError> {
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<Vec2> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut objects: Vec<ObjectBuilder> = Vec::new();
+    let mut material_libraries = Vec::new();
+    let mut material: Option<String> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = strip_comment(line).trim();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let rest = line[keyword.len()..].trim();
+
+        match keyword {
+            "v" => {
+                let values = parse_floats(words, line_number)?;
+                if values.len() < 3 {
+                    return Err(ParseError::new(line_number, "Expected x, y and z for a position"));
+                }
+                positions.push(glm::vec3(values[0], values[1], values[2]));
+            }
+            "vt" => {
+                let values = parse_floats(words, line_number)?;
+                if values.is_empty() {
+                    return Err(ParseError::new(line_number, "Expected at least u for a texture coordinate"));
+                }
+                let v = values.get(1).copied().unwrap_or(0.0);
+                // OBJ has v going up from the bottom of the image, textures are uploaded top row first
+                uvs.push(glm::vec2(values[0], 1.0 - v));
+            }
+            "vn" => {
+                let values = parse_floats(words, line_number)?;
+                if values.len() < 3 {
+                    return Err(ParseError::new(line_number, "Expected x, y and z for a normal"));
+                }
+                normals.push(glm::vec3(values[0], values[1], values[2]));
+            }
+            "f" => {
+                let mut corners = Vec::new();
+                for word in words {
+                    corners.push(parse_corner(word, &positions, &uvs, &normals, line_number)?);
+                }
+                if corners.len() < 3 {
+                    return Err(ParseError::new(line_number, "A face needs at least 3 vertices"));
+                }
+
+                let part = current_part(&mut objects, &material);
+                add_face(part, &corners, &positions, &uvs, &normals, line_number)?;
+            }
+            "o" => objects.push(ObjectBuilder {
+                name: rest.to_string(),
+                groups: Vec::new(),
+            }),
+            "g" => {
+                if objects.is_empty() {
+                    objects.push(ObjectBuilder { name: String::new(), groups: Vec::new() });
+                }
+                let object = objects.last_mut().unwrap();
+                object.groups.push(GroupBuilder {
+                    name: rest.to_string(),
+                    parts: Vec::new(),
+                });
+            }
+            "usemtl" => {
+                if rest.is_empty() {
+                    return Err(ParseError::new(line_number, "usemtl needs a material name"));
+                }
+                material = Some(rest.to_string());
+            }
+            "mtllib" => material_libraries.extend(words.map(String::from)),
+            // smoothing groups, lines, points and the rest are not supported, but harmless
+            _ => {}
+        }
+    }
+
+    // objects and groups without faces don't need game objects
+    let objects = objects
+        .into_iter()
+        .map(|object| ObjObject {
+            name: object.name,
+            groups: object
+                .groups
+                .into_iter()
+                .map(|group| ObjGroup {
+                    name: group.name,
+                    parts: group
+                        .parts
+                        .into_iter()
+                        .map(|part| part.part)
+                        .filter(|part| !part.indices.is_empty())
+                        .collect(),
+                })
+                .filter(|group| !group.parts.is_empty())
+                .collect(),
+        })
+        .filter(|object| !object.groups.is_empty())
+        .collect();
+
+    Ok(ObjModel {
+        objects,
+        material_libraries,
+    })
+}
+
+pub fn parse_mtl(source: &str) -> Result<Vec<MtlMaterial>, ParseError> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = strip_comment(line).trim();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let rest = line[keyword.len()..].trim();
+
+        if keyword == "newmtl" {
+            if rest.is_empty() {
+                return Err(ParseError::new(line_number, "newmtl needs a material name"));
+            }
+            materials.push(MtlMaterial {
+                name: rest.to_string(),
+                diffuse_color: glm::vec3(1.0, 1.0, 1.0),
+                opacity: 1.0,
+                diffuse_texture: None,
+            });
+            continue;
+        }
+
+        if !matches!(keyword, "Kd" | "d" | "Tr" | "map_Kd") {
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            return Err(ParseError::new(line_number, format!("{} before any newmtl", keyword)));
+        };
+
+        match keyword {
+            "Kd" => {
+                let values = parse_floats(words, line_number)?;
+                material.diffuse_color = match values.as_slice() {
+                    [r, g, b, ..] => glm::vec3(*r, *g, *b),
+                    // a single value is used for all channels
+                    [value] => glm::vec3(*value, *value, *value),
+                    _ => return Err(ParseError::new(line_number, "Expected r, g and b for Kd")),
+                };
+            }
+            "d" | "Tr" => {
+                let values = parse_floats(words, line_number)?;
+                let Some(value) = values.first() else {
+                    return Err(ParseError::new(line_number, format!("Expected a value for {}", keyword)));
+                };
+                // `Tr` is transparency, the opposite of `d`
+                material.opacity = if keyword == "d" { *value } else { 1.0 - *value };
+            }
+            "map_Kd" => {
+                // options like `-s 1 1 1` come first, the path is last
+                let Some(path) = words.last() else {
+                    return Err(ParseError::new(line_number, "map_Kd needs a texture path"));
+                };
+                material.diffuse_texture = Some(path.replace('\\', "/"));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+fn parse_floats<'a>(words: impl Iterator<Item = &'a str>, line_number: usize) -> Result<Vec<f32>, ParseError> {
+    words
+        .map(|word| {
+            word.parse::<f32>()
+                .map_err(|_| ParseError::new(line_number, format!("Invalid number '{}'", word)))
+        })
+        .collect()
+}
+
+// Resolves an index of a face corner, negative ones count back from the last element
+fn parse_index(word: &str, count: usize, kind: &str, line_number: usize) -> Result<usize, ParseError> {
+    let index: i64 = word
+        .parse()
+        .map_err(|_| ParseError::new(line_number, format!("Invalid {} index '{}'", kind, word)))?;
+
+    let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ParseError::new(
+            line_number,
+            format!("{} index {} is out of range, there are {} so far", kind, index, count),
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn parse_corner(
+    word: &str,
+    positions: &[Vec3],
+    uvs: &[Vec2],
+    normals: &[Vec3],
+    line_number: usize,
+) -> Result<VertexKey, ParseError> {
+    let mut indices = word.split('/');
+    let position = parse_index(indices.next().unwrap_or(""), positions.len(), "Position", line_number)?;
+    let uv = match indices.next() {
+        Some(uv) if !uv.is_empty() => Some(parse_index(uv, uvs.len(), "Texture coordinate", line_number)?),
+        _ => None,
+    };
+    let normal = match indices.next() {
+        Some(normal) if !normal.is_empty() => Some(parse_index(normal, normals.len(), "Normal", line_number)?),
+        _ => None,
+    };
+    if indices.next().is_some() {
+        return Err(ParseError::new(line_number, format!("Invalid face vertex '{}'", word)));
+    }
+
+    Ok((position, uv, normal))
+}
+
+// The part faces go to right now, creating the object, group or part if needed
+fn current_part<'a>(objects: &'a mut Vec<ObjectBuilder>, material: &Option<String>) -> &'a mut PartBuilder {
+    if objects.is_empty() {
+        objects.push(ObjectBuilder { name: String::new(), groups: Vec::new() });
+    }
+    let object = objects.last_mut().unwrap();
+
+    if object.groups.is_empty() {
+        object.groups.push(GroupBuilder { name: String::new(), parts: Vec::new() });
+    }
+    let group = object.groups.last_mut().unwrap();
+
+    // switching back to a material continues its part
+    let index = match group.parts.iter().position(|part| part.part.material == *material) {
+        Some(index) => index,
+        None => {
+            group.parts.push(PartBuilder {
+                part: ObjPart {
+                    material: material.clone(),
+                    vertices: Vec::new(),
+                    indices: Vec::new(),
+                },
+                emitted: HashMap::new(),
+            });
+            group.parts.len() - 1
+        }
+    };
+    &mut group.parts[index]
+}
+
+// Triangulates the polygon as a fan, which is only right for convex polygons,
+// but that's what exporters write in practice
+fn add_face(
+    part: &mut PartBuilder,
+    corners: &[VertexKey],
+    positions: &[Vec3],
+    uvs: &[Vec2],
+    normals: &[Vec3],
+    line_number: usize,
+) -> Result<(), ParseError> {
+    // corners without a normal get the flat normal of the face
+    let face_normal = polygon_normal(corners.iter().map(|corner| positions[corner.0]));
+
+    let mut indices = Vec::with_capacity(corners.len());
+    for corner in corners {
+        let (position, uv, normal) = *corner;
+        // corners with a flat face normal can't be shared with other faces
+        let is_shared = normal.is_some();
+        if is_shared {
+            if let Some(index) = part.emitted.get(corner) {
+                indices.push(*index);
+                continue;
+            }
+        }
+
+        let index = u32::try_from(part.part.vertices.len() / FLOATS_PER_VERTEX)
+            .map_err(|_| ParseError::new(line_number, "Too many vertices in one mesh"))?;
+        let position = positions[position];
+        let normal = normal.map(|normal| normals[normal]).unwrap_or(face_normal);
+        let uv = uv.map(|uv| uvs[uv]).unwrap_or_else(|| glm::vec2(0.0, 0.0));
+        part.part.vertices.extend_from_slice(&[
+            position.x, position.y, position.z,
+            normal.x, normal.y, normal.z,
+            uv.x, uv.y,
+        ]);
+
+        if is_shared {
+            part.emitted.insert(*corner, index);
+        }
+        indices.push(index);
+    }
+
+    for i in 1..indices.len() - 1 {
+        part.part.indices.extend_from_slice(&[indices[0], indices[i], indices[i + 1]]);
+    }
+    Ok(())
+}
+
+// Newell's method, which also works for polygons that are not quite planar
+fn polygon_normal(points: impl Iterator<Item = Vec3> + Clone) -> Vec3 {
+    let next = points.clone().cycle().skip(1);
+    let normal = points
+        .zip(next)
+        .fold(glm::vec3(0.0, 0.0, 0.0), |normal, (current, next)| {
+            normal
+                + glm::vec3(
+                    (current.y - next.y) * (current.z + next.z),
+                    (current.z - next.z) * (current.x + next.x),
+                    (current.x - next.x) * (current.y + next.y),
+                )
+        });
+
+    if glm::length(&normal) > f32::EPSILON {
+        glm::normalize(&normal)
+    } else {
+        // degenerate face
+        glm::vec3(0.0, 0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    fn only_part(model: &ObjModel) -> &ObjPart {
+        assert_eq!(model.objects.len(), 1);
+        assert_eq!(model.objects[0].groups.len(), 1);
+        assert_eq!(model.objects[0].groups[0].parts.len(), 1);
+        &model.objects[0].groups[0].parts[0]
+    }
+
+    // the vertex at `index` of a part, split into position, normal and UV
+    fn vertex(part: &ObjPart, index: usize) -> (&[f32], &[f32], &[f32]) {
+        let vertex = &part.vertices[index * FLOATS_PER_VERTEX..(index + 1) * FLOATS_PER_VERTEX];
+        (&vertex[0..3], &vertex[3..6], &vertex[6..8])
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let model = parse_obj(&format!("{}f 1 2 3 4\n", SQUARE)).unwrap();
+        assert_eq!(only_part(&model).indices, vec![0, 1, 2, 0, 2, 3]);
+
+        let pentagon = "v 0 0 0\nv 1 0 0\nv 2 1 0\nv 1 2 0\nv 0 1 0\nf 1 2 3 4 5\n";
+        let model = parse_obj(pentagon).unwrap();
+        assert_eq!(only_part(&model).indices.len(), 9);
+    }
+
+    #[test]
+    fn faces_without_normals_get_the_flat_face_normal() {
+        let model = parse_obj(&format!("{}f 1 2 3\n", SQUARE)).unwrap();
+        let (_, normal, _) = vertex(only_part(&model), 0);
+        assert_eq!(normal, &[0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn corners_with_normals_are_shared() {
+        let source = format!("{}vn 0 0 -1\nf 1//1 2//1 3//1\nf 1//1 3//1 4//1\n", SQUARE);
+        let model = parse_obj(&source).unwrap();
+        let part = only_part(&model);
+        assert_eq!(part.vertices.len(), 4 * FLOATS_PER_VERTEX);
+        assert_eq!(part.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(vertex(part, 0).1, &[0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn uvs_are_read_and_flipped_vertically() {
+        let source = format!("{}vt 0.25 0.75\nf 1/1 2/1 3/1\n", SQUARE);
+        let model = parse_obj(&source).unwrap();
+        let (_, _, uv) = vertex(only_part(&model), 0);
+        assert_eq!(uv, &[0.25, 0.25]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let source = format!("{}vt 0 0\nvt 1 1\nf -4/-1 -3/-1 -2/-2\n", SQUARE);
+        let model = parse_obj(&source).unwrap();
+        let part = only_part(&model);
+        assert_eq!(vertex(part, 0).0, &[0.0, 0.0, 0.0]);
+        assert_eq!(vertex(part, 2).0, &[1.0, 1.0, 0.0]);
+        assert_eq!(vertex(part, 0).2, &[1.0, 0.0]);
+        assert_eq!(vertex(part, 2).2, &[0.0, 1.0]);
+    }
+
+    #[test]
+    fn objects_and_groups_are_nested_and_empty_ones_pruned() {
+        let source = format!(
+            "{}mtllib scene.mtl\no Empty\no Box\nf 1 2 3\ng Lid\nf 1 3 4\ng Unused\no Other\ng Handle\nf 1 2 4\n",
+            SQUARE
+        );
+        let model = parse_obj(&source).unwrap();
+
+        assert_eq!(model.material_libraries, vec!["scene.mtl"]);
+        let names: Vec<_> = model.objects.iter().map(|object| object.name.as_str()).collect();
+        assert_eq!(names, vec!["Box", "Other"]);
+
+        let groups: Vec<_> = model.objects[0].groups.iter().map(|group| group.name.as_str()).collect();
+        assert_eq!(groups, vec!["", "Lid"]);
+        assert_eq!(model.objects[1].groups[0].name, "Handle");
+    }
+
+    #[test]
+    fn switching_back_to_a_material_continues_its_part() {
+        let source = format!("{}usemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4\nusemtl red\nf 1 2 4\n", SQUARE);
+        let model = parse_obj(&source).unwrap();
+        let parts = &model.objects[0].groups[0].parts;
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].material.as_deref(), Some("red"));
+        assert_eq!(parts[0].indices.len(), 6);
+        assert_eq!(parts[1].material.as_deref(), Some("blue"));
+        assert_eq!(parts[1].indices.len(), 3);
+    }
+
+    #[test]
+    fn errors_carry_the_line_number() {
+        let err = parse_obj("v 0 0 0\n# comment\nv 1 x 0\n").err().unwrap();
+        assert_eq!(err.line, 3);
+
+        let err = parse_obj("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n").err().unwrap();
+        assert_eq!(err.line, 4);
+        assert!(err.message.contains("out of range"), "{}", err);
+
+        let err = parse_mtl("# materials\nKd 1 0 0\nnewmtl red\n").err().unwrap();
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn mtl_colors_opacity_and_textures_are_read() {
+        let source = "newmtl red\nKd 1 0 0\nd 0.5\nmap_Kd -s 1 1 1 tex.png\n\nnewmtl glass\nKd 0.5\nTr 0.75\n";
+        let materials = parse_mtl(source).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].diffuse_color, glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(materials[0].opacity, 0.5);
+        assert_eq!(materials[0].diffuse_texture.as_deref(), Some("tex.png"));
+
+        assert_eq!(materials[1].diffuse_color, glm::vec3(0.5, 0.5, 0.5));
+        assert_eq!(materials[1].opacity, 0.25);
+        assert_eq!(materials[1].diffuse_texture, None);
+    }
+}
@@ -1,8 +1,7 @@
 use std::{cell::RefCell, rc::Weak};
 
-use web_sys::WebGl2RenderingContext;
-
 use crate::objects::component::{Component, ComponentLogic};
+use crate::renderer::gl_render::GLRender;
 
 pub struct BasicBackground {
     r: f32,
@@ -26,8 +25,9 @@ impl ComponentLogic for BasicBackground {
         // your code here
     }
 
-    fn draw(&self,_component: Weak<RefCell<Component>>, context: &web_sys::WebGl2RenderingContext) {
-        context.clear_color(self.r, self.g, self.b, 1.0);
-        context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+    fn draw(&self,_component: Weak<RefCell<Component>>, renderer: &GLRender) {
+        let backend = renderer.get_backend();
+        backend.set_clear_color(self.r, self.g, self.b, 1.0);
+        backend.clear(true, false);
     }
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use std::{cell::RefCell, rc::Weak};
+
+use glm::Mat4;
+
+use crate::objects::app_state::AppState;
+use crate::objects::component::{Component, ComponentLogic};
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // Vertical field of view in radians
+    Perspective { fov: f32 },
+    // Half of the vertical extent of the view volume, in world units
+    Orthographic { size: f32 },
+}
+
+// The camera looks down the -Z axis of its owning object.
+// Its view matrix is the inverse of the object's world space matrix.
+pub struct Camera {
+    projection: Projection,
+    near: f32,
+    far: f32,
+    aspect: f32,
+}
+
+#[allow(dead_code)]
+impl Camera {
+    pub fn perspective(fov: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Perspective { fov },
+            near,
+            far,
+            aspect: 1.0,
+        }
+    }
+
+    pub fn orthographic(size: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic { size },
+            near,
+            far,
+            aspect: 1.0,
+        }
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn get_near(&self) -> f32 {
+        self.near
+    }
+
+    pub fn set_near(&mut self, near: f32) {
+        self.near = near;
+    }
+
+    pub fn get_far(&self) -> f32 {
+        self.far
+    }
+
+    pub fn set_far(&mut self, far: f32) {
+        self.far = far;
+    }
+
+    pub fn get_aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+
+    fn update_aspect(&mut self, width: u32, height: u32) {
+        // Minimized or hidden canvases report a zero size, keep the last valid aspect then
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov } => glm::perspective(self.aspect, fov, self.near, self.far),
+            Projection::Orthographic { size } => {
+                let half_width = size * self.aspect;
+                glm::ortho(-half_width, half_width, -size, size, self.near, self.far)
+            }
+        }
+    }
+
+    pub fn view_matrix(world_space_matrix: &Mat4) -> Mat4 {
+        glm::inverse(world_space_matrix)
+    }
+}
+
+impl ComponentLogic for Camera {
+    fn start(&mut self, _component: Weak<RefCell<Component>>, state: &AppState) {
+        let (width, height) = state.get_canvas_size();
+        self.update_aspect(width, height);
+    }
+
+    fn on_canvas_resize(&mut self, _component: Weak<RefCell<Component>>, width: u32, height: u32) {
+        self.update_aspect(width, height);
+    }
+}
@@ -0,0 +1,73 @@
+use std::{cell::RefCell, rc::{Rc, Weak}};
+
+use glm::Mat4;
+
+use crate::console;
+use crate::objects::component::{Component, ComponentLogic};
+use crate::objects::transform::Transform;
+use crate::renderer::gl_render::GLRender;
+use crate::renderer::material::Material;
+use crate::renderer::mesh::Mesh;
+
+pub struct MeshRenderer {
+    mesh: Rc<RefCell<Mesh>>,
+    material: Rc<RefCell<Material>>,
+    layer: i32,
+}
+
+#[allow(dead_code)]
+impl MeshRenderer {
+    pub fn new(mesh: Mesh, material: Material) -> Self {
+        Self {
+            mesh: Rc::new(RefCell::new(mesh)),
+            material: Rc::new(RefCell::new(material)),
+            layer: 0,
+        }
+    }
+
+    // Allows several objects to draw the same geometry or material without uploading it twice
+    pub fn from_shared(mesh: Rc<RefCell<Mesh>>, material: Rc<RefCell<Material>>) -> Self {
+        Self { mesh, material, layer: 0 }
+    }
+
+    pub fn get_mesh(&self) -> Rc<RefCell<Mesh>> {
+        self.mesh.clone()
+    }
+
+    pub fn get_material(&self) -> Rc<RefCell<Material>> {
+        self.material.clone()
+    }
+
+    // See `DrawItem::layer`
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    pub fn get_layer(&self) -> i32 {
+        self.layer
+    }
+}
+
+impl ComponentLogic for MeshRenderer {
+    fn draw(&self, component: Weak<RefCell<Component>>, renderer: &GLRender) {
+        {
+            let mut mesh = self.mesh.borrow_mut();
+
+            // Meshes are uploaded lazily, on the first frame they are drawn
+            if !mesh.is_uploaded() {
+                if let Err(err) = mesh.upload(renderer.get_backend()) {
+                    console::error!("Failed to upload mesh: {}", err);
+                    return;
+                }
+            }
+        }
+
+        let model_matrix = component
+            .upgrade()
+            .and_then(|component| component.borrow().get_object().upgrade())
+            .map(|object| object.borrow().get_world_space_matrx())
+            .unwrap_or(Mat4::identity());
+
+        renderer.submit(self.mesh.clone(), self.material.clone(), model_matrix, self.layer);
+    }
+}
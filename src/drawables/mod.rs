@@ -1,2 +1,3 @@
-pub mod basic_triangle;
-pub mod basic_background;
\ No newline at end of file
+pub mod mesh_renderer;
+pub mod basic_background;
+pub mod camera;
\ No newline at end of file
@@ -0,0 +1,462 @@
+use std::collections::BTreeMap;
+
+use crate::input::InputEvent;
+
+// Buttons of the W3C "standard" gamepad mapping, named by position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Home,
+    // Buttons past the standard mapping, or any button of a non-standard gamepad
+    Other(u32),
+}
+
+impl GamepadButton {
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            0 => GamepadButton::South,
+            1 => GamepadButton::East,
+            2 => GamepadButton::West,
+            3 => GamepadButton::North,
+            4 => GamepadButton::LeftBumper,
+            5 => GamepadButton::RightBumper,
+            6 => GamepadButton::LeftTrigger,
+            7 => GamepadButton::RightTrigger,
+            8 => GamepadButton::Select,
+            9 => GamepadButton::Start,
+            10 => GamepadButton::LeftStick,
+            11 => GamepadButton::RightStick,
+            12 => GamepadButton::DPadUp,
+            13 => GamepadButton::DPadDown,
+            14 => GamepadButton::DPadLeft,
+            15 => GamepadButton::DPadRight,
+            16 => GamepadButton::Home,
+            other => GamepadButton::Other(other),
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        match self {
+            GamepadButton::South => 0,
+            GamepadButton::East => 1,
+            GamepadButton::West => 2,
+            GamepadButton::North => 3,
+            GamepadButton::LeftBumper => 4,
+            GamepadButton::RightBumper => 5,
+            GamepadButton::LeftTrigger => 6,
+            GamepadButton::RightTrigger => 7,
+            GamepadButton::Select => 8,
+            GamepadButton::Start => 9,
+            GamepadButton::LeftStick => 10,
+            GamepadButton::RightStick => 11,
+            GamepadButton::DPadUp => 12,
+            GamepadButton::DPadDown => 13,
+            GamepadButton::DPadLeft => 14,
+            GamepadButton::DPadRight => 15,
+            GamepadButton::Home => 16,
+            GamepadButton::Other(index) => *index,
+        }
+    }
+}
+
+// Axes of the standard mapping, +Y points down like in the browser API
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    Other(u32),
+}
+
+impl GamepadAxis {
+    #[allow(dead_code)]
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            0 => GamepadAxis::LeftStickX,
+            1 => GamepadAxis::LeftStickY,
+            2 => GamepadAxis::RightStickX,
+            3 => GamepadAxis::RightStickY,
+            other => GamepadAxis::Other(other),
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        match self {
+            GamepadAxis::LeftStickX => 0,
+            GamepadAxis::LeftStickY => 1,
+            GamepadAxis::RightStickX => 2,
+            GamepadAxis::RightStickY => 3,
+            GamepadAxis::Other(index) => *index,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ButtonData {
+    pub pressed: bool,
+    pub value: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+struct GamepadData {
+    buttons: Vec<ButtonData>,
+    axes: Vec<f32>,
+}
+
+pub struct GamepadState {
+    pads: BTreeMap<u32, GamepadData>,
+    button_presses: Vec<(u32, GamepadButton)>,
+    button_releases: Vec<(u32, GamepadButton)>,
+    connected: Vec<u32>,
+    disconnected: Vec<u32>,
+    deadzone: f32,
+}
+
+impl GamepadState {
+    pub fn new() -> Self {
+        Self {
+            pads: BTreeMap::new(),
+            button_presses: Vec::new(),
+            button_releases: Vec::new(),
+            connected: Vec::new(),
+            disconnected: Vec::new(),
+            deadzone: 0.15,
+        }
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+    }
+
+    // The browser only exposes the current gamepad state, so the polled values are compared
+    // with the known ones and every difference becomes an input event.
+    // Pads missing from `polled` are reported as disconnected.
+    pub fn diff_events(&self, polled: &BTreeMap<u32, (Vec<ButtonData>, Vec<f32>)>, events: &mut Vec<InputEvent>) {
+        for index in self.pads.keys() {
+            if !polled.contains_key(index) {
+                events.push(InputEvent::GamepadDisconnected(*index));
+            }
+        }
+
+        let empty = GamepadData::default();
+        for (index, (buttons, axes)) in polled.iter() {
+            let known = match self.pads.get(index) {
+                Some(known) => known,
+                None => {
+                    events.push(InputEvent::GamepadConnected(*index));
+                    &empty
+                }
+            };
+
+            for (button, data) in buttons.iter().enumerate() {
+                if known.buttons.get(button) != Some(data) {
+                    events.push(InputEvent::GamepadButtonChanged(*index, button as u32, data.pressed, data.value));
+                }
+            }
+
+            for (axis, value) in axes.iter().enumerate() {
+                if known.axes.get(axis) != Some(value) {
+                    events.push(InputEvent::GamepadAxisChanged(*index, axis as u32, *value));
+                }
+            }
+        }
+    }
+
+    pub fn process_events(&mut self, events: &[InputEvent]) {
+        self.button_presses.clear();
+        self.button_releases.clear();
+        self.connected.clear();
+        self.disconnected.clear();
+
+        for event in events.iter() {
+            if let InputEvent::GamepadConnected(pad) = event {
+                self.pads.insert(*pad, GamepadData::default());
+                self.connected.push(*pad);
+                continue;
+            }
+            if let InputEvent::GamepadDisconnected(pad) = event {
+                if let Some(data) = self.pads.remove(pad) {
+                    // held buttons are released, so nothing stays stuck
+                    for (button, state) in data.buttons.iter().enumerate() {
+                        if state.pressed {
+                            self.button_releases.push((*pad, GamepadButton::from_index(button as u32)));
+                        }
+                    }
+                }
+                self.disconnected.push(*pad);
+                continue;
+            }
+            if let InputEvent::GamepadButtonChanged(pad, button, pressed, value) = event {
+                let Some(data) = self.pads.get_mut(pad) else {
+                    continue;
+                };
+                let index = *button as usize;
+                if data.buttons.len() <= index {
+                    data.buttons.resize(index + 1, ButtonData::default());
+                }
+
+                let was_pressed = data.buttons[index].pressed;
+                data.buttons[index] = ButtonData { pressed: *pressed, value: *value };
+
+                if *pressed && !was_pressed {
+                    self.button_presses.push((*pad, GamepadButton::from_index(*button)));
+                }
+                if !*pressed && was_pressed {
+                    self.button_releases.push((*pad, GamepadButton::from_index(*button)));
+                }
+                continue;
+            }
+            if let InputEvent::GamepadAxisChanged(pad, axis, value) = event {
+                let Some(data) = self.pads.get_mut(pad) else {
+                    continue;
+                };
+                let index = *axis as usize;
+                if data.axes.len() <= index {
+                    data.axes.resize(index + 1, 0.0);
+                }
+                data.axes[index] = *value;
+                continue;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> GamepadStateSnapshot {
+        GamepadStateSnapshot::from(self)
+    }
+}
+
+pub struct GamepadStateSnapshot {
+    pads: BTreeMap<u32, GamepadData>,
+    button_presses: Vec<(u32, GamepadButton)>,
+    button_releases: Vec<(u32, GamepadButton)>,
+    connected: Vec<u32>,
+    disconnected: Vec<u32>,
+    deadzone: f32,
+}
+
+#[allow(dead_code)]
+impl GamepadStateSnapshot {
+    pub fn from(state: &GamepadState) -> Self {
+        Self {
+            pads: state.pads.clone(),
+            button_presses: state.button_presses.clone(),
+            button_releases: state.button_releases.clone(),
+            connected: state.connected.clone(),
+            disconnected: state.disconnected.clone(),
+            deadzone: state.deadzone,
+        }
+    }
+
+    pub fn new() -> Self {
+        Self {
+            pads: BTreeMap::new(),
+            button_presses: Vec::new(),
+            button_releases: Vec::new(),
+            connected: Vec::new(),
+            disconnected: Vec::new(),
+            deadzone: 0.15,
+        }
+    }
+
+    pub fn get_connected_gamepads(&self) -> Vec<u32> {
+        self.pads.keys().copied().collect()
+    }
+
+    pub fn is_connected(&self, pad: u32) -> bool {
+        self.pads.contains_key(&pad)
+    }
+
+    pub fn was_connected(&self, pad: u32) -> bool {
+        self.connected.contains(&pad)
+    }
+
+    pub fn was_disconnected(&self, pad: u32) -> bool {
+        self.disconnected.contains(&pad)
+    }
+
+    pub fn is_button_pressed(&self, pad: u32, button: GamepadButton) -> bool {
+        self.get_button(pad, button).pressed
+    }
+
+    pub fn was_button_pressed(&self, pad: u32, button: GamepadButton) -> bool {
+        self.button_presses.contains(&(pad, button))
+    }
+
+    pub fn was_button_released(&self, pad: u32, button: GamepadButton) -> bool {
+        self.button_releases.contains(&(pad, button))
+    }
+
+    // Analog value of the button, 0..1, meaningful mostly for triggers
+    pub fn get_button_value(&self, pad: u32, button: GamepadButton) -> f32 {
+        self.get_button(pad, button).value
+    }
+
+    fn get_button(&self, pad: u32, button: GamepadButton) -> ButtonData {
+        self.pads
+            .get(&pad)
+            .and_then(|data| data.buttons.get(button.index() as usize))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn get_raw_axis(&self, pad: u32, axis: u32) -> f32 {
+        self.pads
+            .get(&pad)
+            .and_then(|data| data.axes.get(axis as usize))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    // Stick axes use a radial deadzone over both axes of the stick,
+    // so small diagonal movements aren't snapped to a single axis.
+    pub fn get_axis(&self, pad: u32, axis: GamepadAxis) -> f32 {
+        match axis {
+            GamepadAxis::LeftStickX => self.get_left_stick(pad).0,
+            GamepadAxis::LeftStickY => self.get_left_stick(pad).1,
+            GamepadAxis::RightStickX => self.get_right_stick(pad).0,
+            GamepadAxis::RightStickY => self.get_right_stick(pad).1,
+            GamepadAxis::Other(index) => {
+                let value = self.get_raw_axis(pad, index);
+                if value.abs() < self.deadzone {
+                    0.0
+                } else {
+                    value.signum() * (value.abs() - self.deadzone) / (1.0 - self.deadzone)
+                }
+            }
+        }
+    }
+
+    pub fn get_left_stick(&self, pad: u32) -> (f32, f32) {
+        self.get_stick(pad, GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)
+    }
+
+    pub fn get_right_stick(&self, pad: u32) -> (f32, f32) {
+        self.get_stick(pad, GamepadAxis::RightStickX, GamepadAxis::RightStickY)
+    }
+
+    fn get_stick(&self, pad: u32, x: GamepadAxis, y: GamepadAxis) -> (f32, f32) {
+        let (x, y) = (self.get_raw_axis(pad, x.index()), self.get_raw_axis(pad, y.index()));
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude < self.deadzone {
+            return (0.0, 0.0);
+        }
+
+        let scaled = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        (x / magnitude * scaled, y / magnitude * scaled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Polled = BTreeMap<u32, (Vec<ButtonData>, Vec<f32>)>;
+
+    fn button(pressed: bool) -> ButtonData {
+        ButtonData { pressed, value: if pressed { 1.0 } else { 0.0 } }
+    }
+
+    fn poll(pad: u32, buttons: Vec<ButtonData>, axes: Vec<f32>) -> Polled {
+        BTreeMap::from([(pad, (buttons, axes))])
+    }
+
+    // Diffs the polled state and applies it, like `AppState::process_events` does
+    fn frame(state: &mut GamepadState, polled: &Polled) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        state.diff_events(polled, &mut events);
+        state.process_events(&events);
+        events
+    }
+
+    fn with_axes(axes: &[(u32, f32)]) -> GamepadStateSnapshot {
+        let mut state = GamepadState::new();
+        let mut events = vec![InputEvent::GamepadConnected(0)];
+        events.extend(axes.iter().map(|(axis, value)| InputEvent::GamepadAxisChanged(0, *axis, *value)));
+        state.process_events(&events);
+        state.snapshot()
+    }
+
+    #[test]
+    fn polled_changes_become_events() {
+        let mut state = GamepadState::new();
+
+        let events = frame(&mut state, &poll(0, vec![button(false), button(true)], vec![0.0]));
+        assert!(matches!(events[0], InputEvent::GamepadConnected(0)));
+        assert!(events.iter().any(|event| matches!(event, InputEvent::GamepadButtonChanged(0, 1, true, _))));
+        assert!(state.snapshot().was_connected(0));
+        assert!(state.snapshot().was_button_pressed(0, GamepadButton::East));
+
+        // nothing changed, nothing to report
+        let events = frame(&mut state, &poll(0, vec![button(false), button(true)], vec![0.0]));
+        assert!(events.is_empty());
+
+        let events = frame(&mut state, &poll(0, vec![button(true), button(true)], vec![-0.5]));
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], InputEvent::GamepadButtonChanged(0, 0, true, _)));
+        assert!(matches!(events[1], InputEvent::GamepadAxisChanged(0, 0, value) if value == -0.5));
+
+        let events = frame(&mut state, &BTreeMap::new());
+        assert!(matches!(events[..], [InputEvent::GamepadDisconnected(0)]));
+        assert!(state.snapshot().was_disconnected(0));
+        assert!(!state.snapshot().is_connected(0));
+    }
+
+    #[test]
+    fn disconnecting_releases_held_buttons() {
+        let mut state = GamepadState::new();
+        frame(&mut state, &poll(3, vec![button(true), button(false)], Vec::new()));
+        assert!(state.snapshot().is_button_pressed(3, GamepadButton::South));
+
+        frame(&mut state, &BTreeMap::new());
+        let snapshot = state.snapshot();
+        assert!(snapshot.was_button_released(3, GamepadButton::South));
+        assert!(!snapshot.was_button_released(3, GamepadButton::East));
+        assert!(!snapshot.is_button_pressed(3, GamepadButton::South));
+    }
+
+    #[test]
+    fn sticks_use_a_radial_deadzone() {
+        // each axis alone is inside the deadzone, together they're outside of it
+        let snapshot = with_axes(&[(0, 0.12), (1, 0.12)]);
+        let (x, y) = snapshot.get_left_stick(0);
+        assert!(x > 0.0 && y > 0.0);
+        assert_eq!(x, y);
+
+        let snapshot = with_axes(&[(2, 0.1), (3, -0.1)]);
+        assert_eq!(snapshot.get_right_stick(0), (0.0, 0.0));
+
+        // past the deadzone the range is rescaled to start at 0 and ends at 1
+        let snapshot = with_axes(&[(0, 0.575), (1, 0.0)]);
+        assert!((snapshot.get_axis(0, GamepadAxis::LeftStickX) - 0.5).abs() < 1e-5);
+        let snapshot = with_axes(&[(0, 0.0), (1, -1.0)]);
+        assert_eq!(snapshot.get_axis(0, GamepadAxis::LeftStickY), -1.0);
+    }
+
+    #[test]
+    fn other_axes_use_a_per_axis_deadzone() {
+        let snapshot = with_axes(&[(4, 0.1), (5, -0.575)]);
+        assert_eq!(snapshot.get_axis(0, GamepadAxis::Other(4)), 0.0);
+        assert!((snapshot.get_axis(0, GamepadAxis::Other(5)) + 0.5).abs() < 1e-5);
+
+        let mut state = GamepadState::new();
+        state.set_deadzone(0.05);
+        state.process_events(&[InputEvent::GamepadConnected(0), InputEvent::GamepadAxisChanged(0, 4, 0.1)]);
+        assert!(state.snapshot().get_axis(0, GamepadAxis::Other(4)) > 0.0);
+    }
+}
@@ -1,9 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use super::key::Key;
+use super::mouse::MouseButton;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyboardInput {
+    // physical key, e.g. `Key::A`, independent of the keyboard layout
+    pub code: Key,
+    // produced value, e.g. "a" or "A", depends on the layout and modifiers
+    pub key: String,
+    pub modifiers: Modifiers,
+    // true for the keydowns a held key keeps firing after the first one
+    pub repeat: bool,
+}
+
+impl From<&web_sys::KeyboardEvent> for KeyboardInput {
+    fn from(e: &web_sys::KeyboardEvent) -> Self {
+        Self {
+            code: Key::from_code(&e.code()),
+            key: e.key(),
+            modifiers: Modifiers {
+                shift: e.shift_key(),
+                ctrl: e.ctrl_key(),
+                alt: e.alt_key(),
+                meta: e.meta_key(),
+            },
+            repeat: e.repeat(),
+        }
+    }
+}
+
 #[allow(dead_code)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum InputEvent {
-    MouseMoved(i32, i32),
-    KeyPressed(u32),
-    KeyReleased(u32),
-    MouseKeyPressed(i32, i32, i16),
-    MouseKeyReleased(i32, i32, i16),
-    ScrollMoved(f64)
+    // positions are in canvas pixels, see `MouseStateSnapshot`
+    MouseMoved(f32, f32),
+    // `movementX`/`movementY`, keeps coming while the pointer is locked
+    MouseRawMoved(f32, f32),
+    PointerLockChanged(bool),
+    KeyPressed(KeyboardInput),
+    KeyReleased(KeyboardInput),
+    MouseKeyPressed(f32, f32, MouseButton),
+    MouseKeyReleased(f32, f32, MouseButton),
+    ScrollMoved(f64),
+    // touch identifier and position in canvas pixels
+    TouchStarted(i32, f32, f32),
+    TouchMoved(i32, f32, f32),
+    TouchEnded(i32, f32, f32),
+    TouchCancelled(i32),
+    // gamepad index, button index, pressed, analog value
+    GamepadButtonChanged(u32, u32, bool, f32),
+    // gamepad index, axis index, value
+    GamepadAxisChanged(u32, u32, f32),
+    GamepadConnected(u32),
+    GamepadDisconnected(u32),
+    // Committed text, possibly several characters at once (e.g. pasted)
+    TextEntered(String),
+    // IME composition, the update carries the whole text composed so far
+    // and the end carries the text that got committed, which may be empty when cancelled
+    CompositionStarted,
+    CompositionUpdated(String),
+    CompositionEnded(String),
+    // The canvas lost focus, keys held at that moment will never get their release event
+    FocusLost
 }
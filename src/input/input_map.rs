@@ -0,0 +1,397 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::key::Key;
+use super::keyboard::KeyboardStateSnapshot;
+use super::mouse::{MouseButton, MouseStateSnapshot};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ActionBinding {
+    Key(Key),
+    MouseButton(MouseButton),
+    // Scrolling is only active during the frame it happened in
+    ScrollUp,
+    ScrollDown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseAxis {
+    X,
+    Y,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    // -1 while `negative` is held, +1 while `positive` is held
+    Keys { negative: Key, positive: Key },
+    MouseButtons { negative: MouseButton, positive: MouseButton },
+    Scroll { scale: f32 },
+    // Uses the raw movement while the pointer is locked, the position doesn't change then
+    MouseDelta { axis: MouseAxis, scale: f32 },
+}
+
+// A named set of bindings, e.g. "gameplay" or "menu"
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputContext {
+    actions: BTreeMap<String, Vec<ActionBinding>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+#[allow(dead_code)]
+impl InputContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind_action(&mut self, action: &str, binding: ActionBinding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind_action(&mut self, action: &str, binding: &ActionBinding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|bound| bound != binding);
+        }
+    }
+
+    // Replaces every binding of the action with the given one
+    pub fn rebind_action(&mut self, action: &str, binding: ActionBinding) {
+        self.actions.insert(action.to_string(), vec![binding]);
+    }
+
+    pub fn get_action_bindings(&self, action: &str) -> &[ActionBinding] {
+        self.actions.get(action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str, binding: &AxisBinding) {
+        if let Some(bindings) = self.axes.get_mut(axis) {
+            bindings.retain(|bound| bound != binding);
+        }
+    }
+
+    pub fn rebind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.insert(axis.to_string(), vec![binding]);
+    }
+
+    pub fn get_axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionState {
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+}
+
+// Translates keyboard and mouse state into named actions and axes.
+// Contexts form a stack and only the topmost one is evaluated,
+// so pushing "menu" over "gameplay" silences gameplay actions until it's popped.
+pub struct InputMap {
+    contexts: BTreeMap<String, InputContext>,
+    context_stack: Vec<String>,
+    actions: HashMap<String, ActionState>,
+    axes: HashMap<String, f32>,
+}
+
+#[allow(dead_code)]
+impl InputMap {
+    pub fn new() -> Self {
+        Self {
+            contexts: BTreeMap::new(),
+            context_stack: Vec::new(),
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn add_context(&mut self, name: &str, context: InputContext) {
+        self.contexts.insert(name.to_string(), context);
+    }
+
+    pub fn remove_context(&mut self, name: &str) -> Option<InputContext> {
+        self.context_stack.retain(|active| active != name);
+        self.contexts.remove(name)
+    }
+
+    pub fn get_context(&self, name: &str) -> Option<&InputContext> {
+        self.contexts.get(name)
+    }
+
+    pub fn get_context_mut(&mut self, name: &str) -> Option<&mut InputContext> {
+        self.contexts.get_mut(name)
+    }
+
+    pub fn push_context(&mut self, name: &str) {
+        self.context_stack.push(name.to_string());
+    }
+
+    pub fn pop_context(&mut self) -> Option<String> {
+        self.context_stack.pop()
+    }
+
+    // Makes the context the only one on the stack
+    pub fn set_context(&mut self, name: &str) {
+        self.context_stack.clear();
+        self.context_stack.push(name.to_string());
+    }
+
+    pub fn get_active_context(&self) -> Option<&str> {
+        self.context_stack.last().map(|name| name.as_str())
+    }
+
+    pub fn update(&mut self, keyboard: &KeyboardStateSnapshot, mouse: &MouseStateSnapshot) {
+        let context = self
+            .context_stack
+            .last()
+            .and_then(|name| self.contexts.get(name));
+
+        let Some(context) = context else {
+            self.actions.clear();
+            self.axes.clear();
+            return;
+        };
+
+        let mut actions = HashMap::new();
+        for (name, bindings) in context.actions.iter() {
+            let held = bindings.iter().any(|binding| Self::is_binding_held(binding, keyboard, mouse));
+            let pressed = bindings.iter().any(|binding| Self::was_binding_pressed(binding, keyboard, mouse));
+            let was_held = self.actions.get(name).map(|state| state.pressed).unwrap_or(false);
+
+            // A press and release within the same frame still counts as both
+            actions.insert(name.clone(), ActionState {
+                pressed: held,
+                just_pressed: !was_held && (held || pressed),
+                just_released: !held && (was_held || pressed),
+            });
+        }
+        self.actions = actions;
+
+        self.axes = context
+            .axes
+            .iter()
+            .map(|(name, bindings)| {
+                let value = bindings
+                    .iter()
+                    .map(|binding| Self::axis_value(binding, keyboard, mouse))
+                    .sum();
+                (name.clone(), value)
+            })
+            .collect();
+    }
+
+    fn is_binding_held(binding: &ActionBinding, keyboard: &KeyboardStateSnapshot, mouse: &MouseStateSnapshot) -> bool {
+        match binding {
+            ActionBinding::Key(key) => keyboard.is_key_pressed(key.clone()),
+            ActionBinding::MouseButton(button) => mouse.is_button_pressed(*button),
+            ActionBinding::ScrollUp => mouse.scroll_delta < 0.0,
+            ActionBinding::ScrollDown => mouse.scroll_delta > 0.0,
+        }
+    }
+
+    fn was_binding_pressed(binding: &ActionBinding, keyboard: &KeyboardStateSnapshot, mouse: &MouseStateSnapshot) -> bool {
+        match binding {
+            ActionBinding::Key(key) => keyboard.was_key_pressed(key.clone()),
+            ActionBinding::MouseButton(button) => mouse.was_button_pressed(*button),
+            ActionBinding::ScrollUp | ActionBinding::ScrollDown => Self::is_binding_held(binding, keyboard, mouse),
+        }
+    }
+
+    fn axis_value(binding: &AxisBinding, keyboard: &KeyboardStateSnapshot, mouse: &MouseStateSnapshot) -> f32 {
+        let digital = |negative: bool, positive: bool| (positive as i32 - negative as i32) as f32;
+
+        match binding {
+            AxisBinding::Keys { negative, positive } => digital(
+                keyboard.is_key_pressed(negative.clone()),
+                keyboard.is_key_pressed(positive.clone()),
+            ),
+            AxisBinding::MouseButtons { negative, positive } => digital(
+                mouse.is_button_pressed(*negative),
+                mouse.is_button_pressed(*positive),
+            ),
+            AxisBinding::Scroll { scale } => mouse.scroll_delta as f32 * scale,
+            AxisBinding::MouseDelta { axis, scale } => {
+                let delta = if mouse.is_pointer_locked { mouse.raw_delta } else { mouse.mouse_delta };
+                let delta = match axis {
+                    MouseAxis::X => delta.0,
+                    MouseAxis::Y => delta.1,
+                };
+                delta * scale
+            }
+        }
+    }
+
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.actions.get(action).map(|state| state.pressed).unwrap_or(false)
+    }
+
+    pub fn was_action_pressed(&self, action: &str) -> bool {
+        self.actions.get(action).map(|state| state.just_pressed).unwrap_or(false)
+    }
+
+    pub fn was_action_released(&self, action: &str) -> bool {
+        self.actions.get(action).map(|state| state.just_released).unwrap_or(false)
+    }
+
+    pub fn get_axis(&self, axis: &str) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+
+    // Serializes the bindings of every context, the context stack is not saved
+    pub fn save_bindings(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.contexts).map_err(|err| err.to_string())
+    }
+
+    // Replaces all contexts with the ones from `save_bindings` output.
+    // Active contexts stay on the stack, as long as they still exist.
+    pub fn load_bindings(&mut self, bindings: &str) -> Result<(), String> {
+        let contexts: BTreeMap<String, InputContext> =
+            serde_json::from_str(bindings).map_err(|err| err.to_string())?;
+
+        self.contexts = contexts;
+        let contexts = &self.contexts;
+        self.context_stack.retain(|name| contexts.contains_key(name));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputEvent, KeyboardInput, KeyboardState, MouseState};
+
+    struct Devices {
+        keyboard: KeyboardState,
+        mouse: MouseState,
+    }
+
+    impl Devices {
+        fn new() -> Self {
+            let mut mouse = MouseState::new();
+            mouse.set_canvas_size(640, 480);
+            Self { keyboard: KeyboardState::new(), mouse }
+        }
+
+        fn frame(&mut self, map: &mut InputMap, events: &[InputEvent]) {
+            self.keyboard.process_events(events);
+            self.mouse.process_events(events);
+            map.update(&self.keyboard.snapshot(), &self.mouse.snapshot());
+        }
+    }
+
+    fn key(code: Key) -> KeyboardInput {
+        KeyboardInput {
+            code,
+            key: String::new(),
+            modifiers: Default::default(),
+            repeat: false,
+        }
+    }
+
+    fn gameplay() -> InputContext {
+        let mut context = InputContext::new();
+        context.bind_action("jump", ActionBinding::Key(Key::Space));
+        context.bind_axis("look_x", AxisBinding::MouseDelta { axis: MouseAxis::X, scale: 0.5 });
+        context
+    }
+
+    #[test]
+    fn rebinding_replaces_every_binding() {
+        let mut context = gameplay();
+        context.bind_action("jump", ActionBinding::MouseButton(MouseButton::Right));
+        context.bind_action("jump", ActionBinding::Key(Key::Space));
+        assert_eq!(context.get_action_bindings("jump").len(), 2);
+
+        context.rebind_action("jump", ActionBinding::Key(Key::W));
+        assert_eq!(context.get_action_bindings("jump"), [ActionBinding::Key(Key::W)]);
+
+        let mut map = InputMap::new();
+        map.add_context("gameplay", context);
+        map.set_context("gameplay");
+        let mut devices = Devices::new();
+        devices.frame(&mut map, &[InputEvent::KeyPressed(key(Key::Space))]);
+        assert!(!map.is_action_pressed("jump"));
+        devices.frame(&mut map, &[InputEvent::KeyPressed(key(Key::W))]);
+        assert!(map.was_action_pressed("jump"));
+    }
+
+    #[test]
+    fn only_the_topmost_context_is_evaluated() {
+        let mut menu = InputContext::new();
+        menu.bind_action("confirm", ActionBinding::Key(Key::Space));
+        let mut map = InputMap::new();
+        map.add_context("gameplay", gameplay());
+        map.add_context("menu", menu);
+        let mut devices = Devices::new();
+
+        map.push_context("gameplay");
+        map.push_context("menu");
+        assert_eq!(map.get_active_context(), Some("menu"));
+        devices.frame(&mut map, &[InputEvent::KeyPressed(key(Key::Space))]);
+        assert!(map.was_action_pressed("confirm"));
+        assert!(!map.is_action_pressed("jump"));
+
+        // the key is still held, so the action counts as pressed again under the new context
+        assert_eq!(map.pop_context().as_deref(), Some("menu"));
+        devices.frame(&mut map, &[]);
+        assert!(map.was_action_pressed("jump"));
+        assert!(!map.is_action_pressed("confirm"));
+
+        map.pop_context();
+        devices.frame(&mut map, &[]);
+        assert!(!map.is_action_pressed("jump"));
+    }
+
+    #[test]
+    fn mouse_delta_axis_uses_raw_movement_under_pointer_lock() {
+        let mut map = InputMap::new();
+        map.add_context("gameplay", gameplay());
+        map.set_context("gameplay");
+        let mut devices = Devices::new();
+
+        devices.frame(&mut map, &[InputEvent::MouseMoved(110.0, 0.0), InputEvent::MouseRawMoved(110.0, 0.0)]);
+        assert_eq!(map.get_axis("look_x"), 55.0);
+
+        devices.frame(&mut map, &[InputEvent::PointerLockChanged(true), InputEvent::MouseRawMoved(40.0, 0.0)]);
+        assert_eq!(map.get_axis("look_x"), 20.0);
+    }
+
+    #[test]
+    fn saved_bindings_load_back() {
+        let mut map = InputMap::new();
+        map.add_context("gameplay", gameplay());
+        map.get_context_mut("gameplay").unwrap().rebind_axis("move_x", AxisBinding::Keys { negative: Key::A, positive: Key::D });
+        map.push_context("gameplay");
+        map.push_context("menu");
+        let saved = map.save_bindings().unwrap();
+
+        let mut loaded = InputMap::new();
+        loaded.add_context("menu", InputContext::new());
+        loaded.push_context("gameplay");
+        loaded.push_context("menu");
+        loaded.load_bindings(&saved).unwrap();
+
+        let context = loaded.get_context("gameplay").unwrap();
+        assert_eq!(context.get_action_bindings("jump"), [ActionBinding::Key(Key::Space)]);
+        assert_eq!(context.get_axis_bindings("move_x"), [AxisBinding::Keys { negative: Key::A, positive: Key::D }]);
+        assert_eq!(context.get_axis_bindings("look_x"), [AxisBinding::MouseDelta { axis: MouseAxis::X, scale: 0.5 }]);
+        // "menu" was never added before saving, so it's gone from the stack
+        assert!(loaded.get_context("menu").is_none());
+        assert_eq!(loaded.get_active_context(), Some("gameplay"));
+
+        assert!(loaded.load_bindings("not json").is_err());
+        assert!(loaded.get_context("gameplay").is_some());
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+// Generates the `Key` enum together with its mapping from and to DOM `KeyboardEvent.code` strings.
+// Codes describe physical key positions, so `Key::A` is the same key on QWERTY and AZERTY layouts.
+macro_rules! keys {
+    ($($variant:ident => $code:literal),* $(,)?) => {
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum Key {
+            $($variant,)*
+            // Any code not listed above, kept verbatim so no key is lost
+            Unknown(String),
+        }
+
+        impl Key {
+            pub fn from_code(code: &str) -> Self {
+                match code {
+                    $($code => Key::$variant,)*
+                    other => Key::Unknown(other.to_string()),
+                }
+            }
+
+            pub fn code(&self) -> &str {
+                match self {
+                    $(Key::$variant => $code,)*
+                    Key::Unknown(code) => code,
+                }
+            }
+        }
+
+        // Keys are stored by their code, so saved bindings stay readable
+        impl From<String> for Key {
+            fn from(code: String) -> Self {
+                Key::from_code(&code)
+            }
+        }
+
+        impl From<Key> for String {
+            fn from(key: Key) -> Self {
+                key.code().to_string()
+            }
+        }
+    };
+}
+
+keys! {
+    A => "KeyA", B => "KeyB", C => "KeyC", D => "KeyD", E => "KeyE", F => "KeyF", G => "KeyG",
+    H => "KeyH", I => "KeyI", J => "KeyJ", K => "KeyK", L => "KeyL", M => "KeyM", N => "KeyN",
+    O => "KeyO", P => "KeyP", Q => "KeyQ", R => "KeyR", S => "KeyS", T => "KeyT", U => "KeyU",
+    V => "KeyV", W => "KeyW", X => "KeyX", Y => "KeyY", Z => "KeyZ",
+
+    Digit0 => "Digit0", Digit1 => "Digit1", Digit2 => "Digit2", Digit3 => "Digit3", Digit4 => "Digit4",
+    Digit5 => "Digit5", Digit6 => "Digit6", Digit7 => "Digit7", Digit8 => "Digit8", Digit9 => "Digit9",
+
+    F1 => "F1", F2 => "F2", F3 => "F3", F4 => "F4", F5 => "F5", F6 => "F6",
+    F7 => "F7", F8 => "F8", F9 => "F9", F10 => "F10", F11 => "F11", F12 => "F12",
+
+    ArrowUp => "ArrowUp", ArrowDown => "ArrowDown", ArrowLeft => "ArrowLeft", ArrowRight => "ArrowRight",
+
+    Space => "Space", Enter => "Enter", Escape => "Escape", Tab => "Tab", Backspace => "Backspace",
+    Delete => "Delete", Insert => "Insert", Home => "Home", End => "End",
+    PageUp => "PageUp", PageDown => "PageDown", CapsLock => "CapsLock", ContextMenu => "ContextMenu",
+    PrintScreen => "PrintScreen", ScrollLock => "ScrollLock", Pause => "Pause",
+
+    ShiftLeft => "ShiftLeft", ShiftRight => "ShiftRight",
+    ControlLeft => "ControlLeft", ControlRight => "ControlRight",
+    AltLeft => "AltLeft", AltRight => "AltRight",
+    MetaLeft => "MetaLeft", MetaRight => "MetaRight",
+
+    Minus => "Minus", Equal => "Equal", BracketLeft => "BracketLeft", BracketRight => "BracketRight",
+    Backslash => "Backslash", Semicolon => "Semicolon", Quote => "Quote", Backquote => "Backquote",
+    Comma => "Comma", Period => "Period", Slash => "Slash", IntlBackslash => "IntlBackslash",
+
+    NumLock => "NumLock",
+    Numpad0 => "Numpad0", Numpad1 => "Numpad1", Numpad2 => "Numpad2", Numpad3 => "Numpad3", Numpad4 => "Numpad4",
+    Numpad5 => "Numpad5", Numpad6 => "Numpad6", Numpad7 => "Numpad7", Numpad8 => "Numpad8", Numpad9 => "Numpad9",
+    NumpadAdd => "NumpadAdd", NumpadSubtract => "NumpadSubtract", NumpadMultiply => "NumpadMultiply",
+    NumpadDivide => "NumpadDivide", NumpadDecimal => "NumpadDecimal", NumpadEnter => "NumpadEnter",
+}
@@ -1,48 +1,104 @@
-use super::input_event::InputEvent;
+use std::collections::HashSet;
+
+use super::input_event::{InputEvent, KeyboardInput, Modifiers};
+use super::key::Key;
 
 pub struct KeyboardState {
-    keys_pressed: [bool; 255],
-    key_presses: Vec<u32>,
-    key_releases: Vec<u32>
+    keys_pressed: HashSet<Key>,
+    key_presses: Vec<Key>,
+    key_repeats: Vec<Key>,
+    key_releases: Vec<Key>,
+    key_inputs: Vec<KeyboardInput>,
+    text: String,
+    composition: Option<String>,
+    modifiers: Modifiers
 }
 
 impl KeyboardState {
     pub fn new() -> Self {
         Self {
-            keys_pressed: [false; 255],
+            keys_pressed: HashSet::new(),
             key_presses: Vec::new(),
-            key_releases: Vec::new()
+            key_repeats: Vec::new(),
+            key_releases: Vec::new(),
+            key_inputs: Vec::new(),
+            text: String::new(),
+            composition: None,
+            modifiers: Modifiers::default()
         }
     }
 
-    pub fn process_events(&mut self, events: &Vec<InputEvent>) {
+    pub fn process_events(&mut self, events: &[InputEvent]) {
         self.key_presses.clear();
+        self.key_repeats.clear();
         self.key_releases.clear();
+        self.key_inputs.clear();
+        self.text.clear();
 
         for event in events.iter() {
-            if let InputEvent::KeyPressed(key) = event {
-                self.keys_pressed[*key as usize] = true;
-                self.key_presses.push(key.clone());
+            if let InputEvent::KeyPressed(input) = event {
+                self.keys_pressed.insert(input.code.clone());
+                // held keys fire keydown repeatedly, only the first one is a press
+                if input.repeat {
+                    self.key_repeats.push(input.code.clone());
+                } else {
+                    self.key_presses.push(input.code.clone());
+                }
+                self.key_inputs.push(input.clone());
+                self.modifiers = input.modifiers;
+                continue;
+            }
+            if let InputEvent::KeyReleased(input) = event {
+                self.keys_pressed.remove(&input.code);
+                self.key_releases.push(input.code.clone());
+                self.modifiers = input.modifiers;
+                continue;
+            }
+            if let InputEvent::TextEntered(text) = event {
+                self.text.push_str(text);
+                continue;
+            }
+            if let InputEvent::CompositionStarted = event {
+                self.composition = Some(String::new());
                 continue;
             }
-            if let InputEvent::KeyReleased(key) = event {
-                self.keys_pressed[*key as usize] = false;
-                self.key_releases.push(key.clone());
+            if let InputEvent::CompositionUpdated(text) = event {
+                self.composition = Some(text.clone());
+                continue;
+            }
+            if let InputEvent::CompositionEnded(text) = event {
+                self.composition = None;
+                self.text.push_str(text);
+                continue;
+            }
+            if let InputEvent::FocusLost = event {
+                self.release_all();
+                self.composition = None;
                 continue;
             }
         }
     }
 
+    fn release_all(&mut self) {
+        self.key_releases.extend(self.keys_pressed.drain());
+        self.modifiers = Modifiers::default();
+    }
+
     pub fn snapshot(&self) -> KeyboardStateSnapshot {
-        KeyboardStateSnapshot::from(&self)
+        KeyboardStateSnapshot::from(self)
     }
 }
 
 #[allow(dead_code)]
 pub struct KeyboardStateSnapshot {
-    keys_pressed: [bool; 255],
-    key_presses: Vec<u32>,
-    key_releases: Vec<u32>
+    keys_pressed: HashSet<Key>,
+    key_presses: Vec<Key>,
+    key_repeats: Vec<Key>,
+    key_releases: Vec<Key>,
+    key_inputs: Vec<KeyboardInput>,
+    text: String,
+    composition: Option<String>,
+    pub modifiers: Modifiers
 }
 
 #[allow(dead_code)]
@@ -51,29 +107,94 @@ impl KeyboardStateSnapshot {
     pub fn from(state: &KeyboardState) -> Self {
         Self {
             key_presses: state.key_presses.clone(),
+            key_repeats: state.key_repeats.clone(),
             key_releases: state.key_releases.clone(),
-            keys_pressed: state.keys_pressed.clone()
+            keys_pressed: state.keys_pressed.clone(),
+            key_inputs: state.key_inputs.clone(),
+            text: state.text.clone(),
+            composition: state.composition.clone(),
+            modifiers: state.modifiers
         }
     }
 
     pub fn new() -> Self {
         Self {
-            keys_pressed: [false; 255],
+            keys_pressed: HashSet::new(),
             key_presses: Vec::new(),
-            key_releases: Vec::new()
+            key_repeats: Vec::new(),
+            key_releases: Vec::new(),
+            key_inputs: Vec::new(),
+            text: String::new(),
+            composition: None,
+            modifiers: Modifiers::default()
         }
     }
 
-    pub fn is_key_pressed(&self, key: u32) -> bool {
-        self.keys_pressed[key as usize]
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
     }
 
-    pub fn was_key_pressed(&self, key: u32) -> bool {
+    pub fn was_key_pressed(&self, key: Key) -> bool {
         self.key_presses.contains(&key)
     }
 
-    pub fn was_key_released(&self, key: u32) -> bool {
+    // Auto-repeat of a held key, e.g. for moving a text caret
+    pub fn was_key_repeated(&self, key: Key) -> bool {
+        self.key_repeats.contains(&key)
+    }
+
+    pub fn was_key_released(&self, key: Key) -> bool {
         self.key_releases.contains(&key)
     }
+
+    // Key presses of this frame, repeats included, in the order they happened
+    pub fn get_key_inputs(&self) -> &[KeyboardInput] {
+        &self.key_inputs
+    }
+
+    // Text committed during this frame, only filled while text input is active.
+    // See `AppState::start_text_input`.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    // Text of the ongoing IME composition, not committed yet.
+    // UI should display it in place of the caret, it's replaced on every update.
+    pub fn get_composition(&self) -> Option<&str> {
+        self.composition.as_deref()
+    }
+
+    pub fn is_composing(&self) -> bool {
+        self.composition.is_some()
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_down(code: Key, repeat: bool) -> InputEvent {
+        InputEvent::KeyPressed(KeyboardInput {
+            code,
+            key: String::new(),
+            modifiers: Modifiers::default(),
+            repeat,
+        })
+    }
+
+    #[test]
+    fn repeats_are_not_presses() {
+        let mut state = KeyboardState::new();
+        state.process_events(&[key_down(Key::A, false)]);
+        let snapshot = state.snapshot();
+        assert!(snapshot.was_key_pressed(Key::A));
+        assert!(!snapshot.was_key_repeated(Key::A));
+
+        state.process_events(&[key_down(Key::A, true), key_down(Key::A, true)]);
+        let snapshot = state.snapshot();
+        assert!(snapshot.is_key_pressed(Key::A));
+        assert!(!snapshot.was_key_pressed(Key::A));
+        assert!(snapshot.was_key_repeated(Key::A));
+        assert_eq!(snapshot.get_key_inputs().len(), 2);
+    }
+}
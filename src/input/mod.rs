@@ -1,7 +1,17 @@
 mod mouse;
 mod keyboard;
+mod key;
+mod gamepad;
+mod recording;
+mod touch;
 mod input_event;
+mod input_map;
 
-pub use mouse::{MouseState, MouseStateSnapshot};
+pub use mouse::{MouseButton, MouseState, MouseStateSnapshot};
 pub use keyboard::{KeyboardState, KeyboardStateSnapshot};
-pub use input_event::InputEvent;
\ No newline at end of file
+pub use key::Key;
+pub use gamepad::{ButtonData, GamepadAxis, GamepadButton, GamepadState, GamepadStateSnapshot};
+pub use touch::{PanGesture, PinchGesture, Touch, TouchPhase, TouchState, TouchStateSnapshot};
+pub use input_event::{InputEvent, KeyboardInput};
+pub use input_map::{ActionBinding, AxisBinding, InputContext, InputMap, MouseAxis};
+pub use recording::{InputRecorder, InputRecording, InputReplay};
\ No newline at end of file
@@ -1,45 +1,98 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
 use crate::input::InputEvent;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    Back,
+    Forward,
+    // Buttons beyond the five standard ones, by their `MouseEvent.button` number
+    Other(i16),
+}
+
+impl MouseButton {
+    pub fn from_button(button: i16) -> Self {
+        match button {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            3 => MouseButton::Back,
+            4 => MouseButton::Forward,
+            other => MouseButton::Other(other),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn button(&self) -> i16 {
+        match self {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::Back => 3,
+            MouseButton::Forward => 4,
+            MouseButton::Other(button) => *button,
+        }
+    }
+}
+
 pub struct MouseState {
-    keys_pressed: [bool; 9],
+    keys_pressed: HashSet<MouseButton>,
     pub scroll_delta: f64,
-    pub position: (i32, i32),
-    pub mouse_delta: (i32, i32),
-    key_presses: Vec<i16>,
-    key_releases: Vec<i16>
+    pub position: (f32, f32),
+    pub mouse_delta: (f32, f32),
+    pub raw_delta: (f32, f32),
+    pub is_pointer_locked: bool,
+    canvas_size: (u32, u32),
+    key_presses: Vec<MouseButton>,
+    key_releases: Vec<MouseButton>
 }
 
 impl MouseState {
     pub fn new() -> Self {
         Self {
-            keys_pressed: [false; 9],
+            keys_pressed: HashSet::new(),
             scroll_delta: 0.0,
-            position: (0, 0),
-            mouse_delta: (0, 0),
+            position: (0.0, 0.0),
+            mouse_delta: (0.0, 0.0),
+            raw_delta: (0.0, 0.0),
+            is_pointer_locked: false,
+            canvas_size: (0, 0),
             key_presses: Vec::new(),
             key_releases: Vec::new(),
         }
     }
 
-    pub fn process_events(&mut self, events: &Vec<InputEvent>) {
+    pub fn process_events(&mut self, events: &[InputEvent]) {
         self.key_presses.clear();
         self.key_releases.clear();
         self.scroll_delta = 0.0;
+        self.raw_delta = (0.0, 0.0);
         let mouse_prev = self.position;
         for event in events.iter() {
             if let InputEvent::MouseKeyPressed(_, _, key) = event {
-                self.keys_pressed[*key as usize] = true;
-                self.key_presses.push(key.clone());
+                self.keys_pressed.insert(*key);
+                self.key_presses.push(*key);
                 continue;
             }
             if let InputEvent::MouseKeyReleased(_, _, key) = event {
-                self.keys_pressed[*key as usize] = false;
-                self.key_releases.push(key.clone());
+                self.keys_pressed.remove(key);
+                self.key_releases.push(*key);
                 continue;
             }
             if let InputEvent::MouseMoved(x, y ) = event {
                 self.position = (*x, *y)
             }
+            if let InputEvent::MouseRawMoved(x, y) = event {
+                self.raw_delta = (self.raw_delta.0 + x, self.raw_delta.1 + y)
+            }
+            if let InputEvent::PointerLockChanged(is_locked) = event {
+                self.is_pointer_locked = *is_locked
+            }
             if let InputEvent::ScrollMoved(delta) = event  {
                 self.scroll_delta += delta
             }
@@ -48,19 +101,31 @@ impl MouseState {
         self.mouse_delta = (self.position.0 - mouse_prev.0, self.position.1 - mouse_prev.1)
     }
 
+    pub fn set_canvas_size(&mut self, width: u32, height: u32) {
+        self.canvas_size = (width, height);
+    }
+
     pub fn snapshot(&self) -> MouseStateSnapshot {
-        MouseStateSnapshot::from(&self)
+        MouseStateSnapshot::from(self)
     }
 }
 
+// Positions are in canvas pixels, with (0, 0) in the top left corner of the canvas,
+// `position_ndc` is the same position in normalized device coordinates, with +Y pointing up.
+// `mouse_delta` stops at the canvas edges and freezes while the pointer is locked,
+// `raw_delta` is the unbounded movement reported by the browser, use it for mouse-look.
 #[allow(dead_code)]
 pub struct MouseStateSnapshot {
-    keys_pressed: [bool; 9],
+    keys_pressed: HashSet<MouseButton>,
     pub scroll_delta: f64,
-    pub position: (i32, i32),
-    pub mouse_delta: (i32, i32),
-    key_presses: Vec<i16>,
-    key_releases: Vec<i16>
+    pub position: (f32, f32),
+    pub position_ndc: (f32, f32),
+    pub mouse_delta: (f32, f32),
+    pub raw_delta: (f32, f32),
+    pub is_pointer_locked: bool,
+    pub canvas_size: (u32, u32),
+    key_presses: Vec<MouseButton>,
+    key_releases: Vec<MouseButton>
 }
 
 #[allow(dead_code)]
@@ -70,9 +135,13 @@ impl MouseStateSnapshot {
     pub fn from(state: &MouseState) -> Self {
         Self {
             keys_pressed: state.keys_pressed.clone(),
-            scroll_delta: state.scroll_delta.clone(),
-            position: state.position.clone(),
-            mouse_delta: state.mouse_delta.clone(),
+            scroll_delta: state.scroll_delta,
+            position: state.position,
+            position_ndc: canvas_to_ndc(state.position, state.canvas_size),
+            mouse_delta: state.mouse_delta,
+            raw_delta: state.raw_delta,
+            is_pointer_locked: state.is_pointer_locked,
+            canvas_size: state.canvas_size,
             key_presses: state.key_presses.clone(),
             key_releases: state.key_releases.clone(),
         }
@@ -80,36 +149,58 @@ impl MouseStateSnapshot {
 
     pub fn new() -> Self {
         Self {
-            keys_pressed: [false; 9],
+            keys_pressed: HashSet::new(),
             scroll_delta: 0.0,
-            position: (0, 0),
-            mouse_delta: (0, 0),
+            position: (0.0, 0.0),
+            position_ndc: (0.0, 0.0),
+            mouse_delta: (0.0, 0.0),
+            raw_delta: (0.0, 0.0),
+            is_pointer_locked: false,
+            canvas_size: (0, 0),
             key_presses: Vec::new(),
             key_releases: Vec::new(),
         }
     }
 
-    pub fn is_button_pressed(&self, key: i16) -> bool {
-        self.keys_pressed[key as usize]
+    pub fn canvas_to_ndc(&self, position: (f32, f32)) -> (f32, f32) {
+        canvas_to_ndc(position, self.canvas_size)
+    }
+
+    pub fn ndc_to_canvas(&self, position: (f32, f32)) -> (f32, f32) {
+        let (width, height) = (self.canvas_size.0 as f32, self.canvas_size.1 as f32);
+        ((position.0 + 1.0) * 0.5 * width, (1.0 - position.1) * 0.5 * height)
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.keys_pressed.contains(&button)
     }
 
-    pub fn was_button_pressed(&self, key: i16) -> bool {
-        self.key_presses.contains(&key)
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.key_presses.contains(&button)
     }
 
-    pub fn was_button_released(&self, key: i16) -> bool {
-        self.key_releases.contains(&key)
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        self.key_releases.contains(&button)
     }
 
     pub fn is_lmb_pressed(&self) -> bool {
-        self.is_button_pressed(0)
+        self.is_button_pressed(MouseButton::Left)
     }
 
     pub fn is_mmb_pressed(&self) -> bool {
-        self.is_button_pressed(1)
+        self.is_button_pressed(MouseButton::Middle)
     }
 
     pub fn is_rmb_pressed(&self) -> bool {
-        self.is_button_pressed(2)
+        self.is_button_pressed(MouseButton::Right)
+    }
+}
+
+fn canvas_to_ndc(position: (f32, f32), canvas_size: (u32, u32)) -> (f32, f32) {
+    if canvas_size.0 == 0 || canvas_size.1 == 0 {
+        return (0.0, 0.0);
     }
-}
\ No newline at end of file
+
+    let (width, height) = (canvas_size.0 as f32, canvas_size.1 as f32);
+    (position.0 / width * 2.0 - 1.0, 1.0 - position.1 / height * 2.0)
+}
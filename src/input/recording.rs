@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::input::InputEvent;
+
+// Bumped whenever the serialized layout of `InputEvent` or the recording changes
+const RECORDING_VERSION: u32 = 3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub frame: u64,
+    // The `Time` input of the frame, scaled values follow from it
+    pub time: f32,
+    pub unscaled_delta_time: f32,
+    // false for frames skipped while paused
+    pub is_frame_running: bool,
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputRecording {
+    version: u32,
+    frames: Vec<RecordedFrame>,
+}
+
+#[allow(dead_code)]
+impl InputRecording {
+    pub fn new() -> Self {
+        Self {
+            version: RECORDING_VERSION,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn get_frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        bincode::serialize(self).map_err(|err| err.to_string())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let recording: Self = bincode::deserialize(bytes).map_err(|err| err.to_string())?;
+        if recording.version != RECORDING_VERSION {
+            return Err(format!(
+                "Unsupported recording version {}, expected {}",
+                recording.version, RECORDING_VERSION
+            ));
+        }
+        Ok(recording)
+    }
+}
+
+pub struct InputRecorder {
+    recording: InputRecording,
+    frame: u64,
+}
+
+impl InputRecorder {
+    pub fn new() -> Self {
+        Self {
+            recording: InputRecording::new(),
+            frame: 0,
+        }
+    }
+
+    pub fn record_frame(&mut self, time: f32, unscaled_delta_time: f32, is_frame_running: bool, events: Vec<InputEvent>) {
+        self.recording.frames.push(RecordedFrame {
+            frame: self.frame,
+            time,
+            unscaled_delta_time,
+            is_frame_running,
+            events,
+        });
+        self.frame += 1;
+    }
+
+    pub fn finish(self) -> InputRecording {
+        self.recording
+    }
+}
+
+pub struct InputReplay {
+    recording: InputRecording,
+    cursor: usize,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            cursor: 0,
+        }
+    }
+
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let frame = self.recording.frames.get(self.cursor).cloned();
+        self.cursor += 1;
+        frame
+    }
+
+    #[allow(dead_code)]
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.recording.frames.len()
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+
+use crate::input::InputEvent;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Stationary,
+    // Ended and cancelled touches are reported for one frame, then removed
+    Ended,
+    Cancelled,
+}
+
+// Positions are in canvas pixels, same as `MouseStateSnapshot::position`
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub struct Touch {
+    pub id: i32,
+    pub phase: TouchPhase,
+    pub position: (f32, f32),
+    pub start_position: (f32, f32),
+    pub delta: (f32, f32),
+    // Also set when the touch ended in the same frame it started, its phase is Ended then
+    pub started_this_frame: bool,
+}
+
+impl Touch {
+    pub fn is_active(&self) -> bool {
+        !matches!(self.phase, TouchPhase::Ended | TouchPhase::Cancelled)
+    }
+
+    fn move_to(&mut self, position: (f32, f32)) {
+        self.delta = (
+            self.delta.0 + position.0 - self.position.0,
+            self.delta.1 + position.1 - self.position.1,
+        );
+        self.position = position;
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PinchGesture {
+    pub center: (f32, f32),
+    // Change of the distance between fingers since the previous frame, > 1 when spreading
+    pub scale: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PanGesture {
+    pub center: (f32, f32),
+    pub delta: (f32, f32),
+}
+
+pub struct TouchState {
+    touches: BTreeMap<i32, Touch>,
+    pinch: Option<PinchGesture>,
+    pan: Option<PanGesture>,
+}
+
+impl TouchState {
+    pub fn new() -> Self {
+        Self {
+            touches: BTreeMap::new(),
+            pinch: None,
+            pan: None,
+        }
+    }
+
+    pub fn process_events(&mut self, events: &[InputEvent]) {
+        self.touches.retain(|_, touch| touch.is_active());
+        for touch in self.touches.values_mut() {
+            touch.phase = TouchPhase::Stationary;
+            touch.delta = (0.0, 0.0);
+            touch.started_this_frame = false;
+        }
+
+        for event in events.iter() {
+            if let InputEvent::TouchStarted(id, x, y) = event {
+                self.touches.insert(*id, Touch {
+                    id: *id,
+                    phase: TouchPhase::Started,
+                    position: (*x, *y),
+                    start_position: (*x, *y),
+                    delta: (0.0, 0.0),
+                    started_this_frame: true,
+                });
+                continue;
+            }
+            if let InputEvent::TouchMoved(id, x, y) = event {
+                if let Some(touch) = self.touches.get_mut(id) {
+                    touch.move_to((*x, *y));
+                    if touch.phase == TouchPhase::Stationary {
+                        touch.phase = TouchPhase::Moved;
+                    }
+                }
+                continue;
+            }
+            if let InputEvent::TouchEnded(id, x, y) = event {
+                if let Some(touch) = self.touches.get_mut(id) {
+                    touch.move_to((*x, *y));
+                    touch.phase = TouchPhase::Ended;
+                }
+                continue;
+            }
+            if let InputEvent::TouchCancelled(id) = event {
+                if let Some(touch) = self.touches.get_mut(id) {
+                    touch.phase = TouchPhase::Cancelled;
+                }
+                continue;
+            }
+        }
+
+        self.recognize_gestures();
+    }
+
+    // Two-finger gestures are recognized only while exactly two fingers stay on the screen,
+    // touches that just started have no previous position to compare with.
+    fn recognize_gestures(&mut self) {
+        self.pinch = None;
+        self.pan = None;
+
+        let fingers: Vec<&Touch> = self
+            .touches
+            .values()
+            .filter(|touch| matches!(touch.phase, TouchPhase::Moved | TouchPhase::Stationary))
+            .collect();
+        if fingers.len() != 2 || self.touches.len() != 2 {
+            return;
+        }
+
+        let (first, second) = (fingers[0], fingers[1]);
+        let previous = |touch: &Touch| (touch.position.0 - touch.delta.0, touch.position.1 - touch.delta.1);
+        let center = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) * 0.5, (a.1 + b.1) * 0.5);
+        let distance = |a: (f32, f32), b: (f32, f32)| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+
+        let current_center = center(first.position, second.position);
+        let previous_center = center(previous(first), previous(second));
+        let current_distance = distance(first.position, second.position);
+        let previous_distance = distance(previous(first), previous(second));
+
+        if previous_distance > 0.0 && current_distance != previous_distance {
+            self.pinch = Some(PinchGesture {
+                center: current_center,
+                scale: current_distance / previous_distance,
+            });
+        }
+
+        if current_center != previous_center {
+            self.pan = Some(PanGesture {
+                center: current_center,
+                delta: (current_center.0 - previous_center.0, current_center.1 - previous_center.1),
+            });
+        }
+    }
+
+    pub fn snapshot(&self) -> TouchStateSnapshot {
+        TouchStateSnapshot::from(self)
+    }
+}
+
+pub struct TouchStateSnapshot {
+    touches: Vec<Touch>,
+    pub pinch: Option<PinchGesture>,
+    pub pan: Option<PanGesture>,
+}
+
+#[allow(dead_code)]
+impl TouchStateSnapshot {
+    pub fn from(state: &TouchState) -> Self {
+        Self {
+            touches: state.touches.values().copied().collect(),
+            pinch: state.pinch,
+            pan: state.pan,
+        }
+    }
+
+    pub fn new() -> Self {
+        Self {
+            touches: Vec::new(),
+            pinch: None,
+            pan: None,
+        }
+    }
+
+    // All touches of this frame, including the ones that ended during it
+    pub fn get_touches(&self) -> &[Touch] {
+        &self.touches
+    }
+
+    pub fn get_touch(&self, id: i32) -> Option<&Touch> {
+        self.touches.iter().find(|touch| touch.id == id)
+    }
+
+    pub fn active_touch_count(&self) -> usize {
+        self.touches.iter().filter(|touch| touch.is_active()).count()
+    }
+
+    pub fn was_touch_started(&self) -> bool {
+        self.touches.iter().any(|touch| touch.started_this_frame)
+    }
+
+    pub fn was_touch_ended(&self) -> bool {
+        self.touches.iter().any(|touch| touch.phase == TouchPhase::Ended)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(state: &TouchState, id: i32) -> Option<TouchPhase> {
+        state.snapshot().get_touch(id).map(|touch| touch.phase)
+    }
+
+    #[test]
+    fn touches_go_through_their_phases() {
+        let mut state = TouchState::new();
+
+        state.process_events(&[InputEvent::TouchStarted(1, 10.0, 10.0)]);
+        assert_eq!(phase(&state, 1), Some(TouchPhase::Started));
+        assert!(state.snapshot().was_touch_started());
+
+        state.process_events(&[]);
+        assert_eq!(phase(&state, 1), Some(TouchPhase::Stationary));
+        assert!(!state.snapshot().was_touch_started());
+
+        state.process_events(&[InputEvent::TouchMoved(1, 15.0, 12.0), InputEvent::TouchMoved(1, 20.0, 14.0)]);
+        let snapshot = state.snapshot();
+        let touch = snapshot.get_touch(1).unwrap();
+        assert_eq!(touch.phase, TouchPhase::Moved);
+        assert_eq!(touch.delta, (10.0, 4.0));
+        assert_eq!(touch.start_position, (10.0, 10.0));
+
+        state.process_events(&[InputEvent::TouchEnded(1, 25.0, 14.0)]);
+        let snapshot = state.snapshot();
+        assert_eq!(phase(&state, 1), Some(TouchPhase::Ended));
+        assert!(snapshot.was_touch_ended());
+        assert_eq!(snapshot.active_touch_count(), 0);
+
+        state.process_events(&[]);
+        assert_eq!(phase(&state, 1), None);
+    }
+
+    #[test]
+    fn touch_started_and_ended_in_one_frame_reports_both() {
+        let mut state = TouchState::new();
+
+        state.process_events(&[InputEvent::TouchStarted(3, 5.0, 5.0), InputEvent::TouchEnded(3, 6.0, 5.0)]);
+        let snapshot = state.snapshot();
+        let touch = snapshot.get_touch(3).unwrap();
+        assert!(touch.started_this_frame);
+        assert_eq!(touch.phase, TouchPhase::Ended);
+        assert!(snapshot.was_touch_started());
+        assert!(snapshot.was_touch_ended());
+
+        state.process_events(&[]);
+        assert!(state.snapshot().get_touches().is_empty());
+    }
+
+    #[test]
+    fn cancelled_touches_are_reported_once() {
+        let mut state = TouchState::new();
+        state.process_events(&[InputEvent::TouchStarted(1, 0.0, 0.0)]);
+
+        state.process_events(&[InputEvent::TouchCancelled(1)]);
+        assert_eq!(phase(&state, 1), Some(TouchPhase::Cancelled));
+        assert!(!state.snapshot().was_touch_ended());
+
+        state.process_events(&[]);
+        assert_eq!(phase(&state, 1), None);
+    }
+
+    #[test]
+    fn spreading_fingers_is_a_pinch() {
+        let mut state = TouchState::new();
+        state.process_events(&[InputEvent::TouchStarted(1, 40.0, 50.0), InputEvent::TouchStarted(2, 60.0, 50.0)]);
+        assert!(state.pinch.is_none());
+
+        state.process_events(&[InputEvent::TouchMoved(1, 30.0, 50.0), InputEvent::TouchMoved(2, 70.0, 50.0)]);
+        let pinch = state.pinch.unwrap();
+        assert_eq!(pinch.scale, 2.0);
+        assert_eq!(pinch.center, (50.0, 50.0));
+        assert!(state.pan.is_none());
+
+        state.process_events(&[]);
+        assert!(state.pinch.is_none());
+    }
+
+    #[test]
+    fn moving_fingers_together_is_a_pan() {
+        let mut state = TouchState::new();
+        state.process_events(&[InputEvent::TouchStarted(1, 40.0, 50.0), InputEvent::TouchStarted(2, 60.0, 50.0)]);
+
+        state.process_events(&[InputEvent::TouchMoved(1, 50.0, 45.0), InputEvent::TouchMoved(2, 70.0, 45.0)]);
+        let pan = state.pan.unwrap();
+        assert_eq!(pan.delta, (10.0, -5.0));
+        assert_eq!(pan.center, (60.0, 45.0));
+        assert!(state.pinch.is_none());
+    }
+
+    #[test]
+    fn gestures_need_exactly_two_fingers() {
+        let mut state = TouchState::new();
+        state.process_events(&[
+            InputEvent::TouchStarted(1, 40.0, 50.0),
+            InputEvent::TouchStarted(2, 60.0, 50.0),
+            InputEvent::TouchStarted(3, 80.0, 50.0),
+        ]);
+
+        state.process_events(&[InputEvent::TouchMoved(1, 30.0, 50.0), InputEvent::TouchMoved(2, 70.0, 50.0)]);
+        assert!(state.pinch.is_none());
+        assert!(state.pan.is_none());
+
+        // A finger that just landed has no previous position yet
+        state.process_events(&[InputEvent::TouchEnded(3, 80.0, 50.0)]);
+        state.process_events(&[]);
+        state.process_events(&[InputEvent::TouchStarted(4, 0.0, 0.0), InputEvent::TouchMoved(1, 20.0, 50.0)]);
+        assert!(state.pinch.is_none());
+        assert!(state.pan.is_none());
+    }
+}
@@ -1,14 +1,28 @@
 use std::panic;
+use std::rc::Rc;
 
+use assets::AssetManager;
 use drawables::basic_background::BasicBackground;
-use drawables::basic_triangle::BasicTriangle;
+use drawables::camera::Camera;
+use drawables::mesh_renderer::MeshRenderer;
 use objects::game_object::GameObject;
+use objects::transform::Transform;
+use renderer::material::Material;
+use renderer::mesh::{Mesh, VertexLayout};
+use renderer::render_backend::RenderBackend;
+use renderer::shader_program::ShaderProgram;
+use renderer::webgl_backend::WebGlBackend;
 use wasm_bindgen::prelude::*;
-use web_sys::{window, WebGl2RenderingContext, WebGlProgram, WebGlShader};
+use web_sys::{window, WebGl2RenderingContext};
 
+mod assets;
 mod console;
 extern crate nalgebra_glm as glm;
 
+use input::{
+    ActionBinding, AxisBinding, GamepadAxis, GamepadButton, InputContext, Key, MouseAxis, MouseButton, PanGesture,
+    PinchGesture, Touch, TouchPhase,
+};
 use objects::app_state::AppState;
 
 mod drawables;
@@ -19,12 +33,6 @@ mod input;
 
 static mut WINDOW_ANIMATION_FRAME_REQUEST_CLOSURE: Option<Closure<dyn FnMut()>> = None;
 
-#[wasm_bindgen(raw_module="/asset-utils.js")]
-extern "C" {
-    #[wasm_bindgen(js_name=getAsset)]
-    async fn get_asset(name: &str) -> JsValue;
-}
-
 fn get_canvas() -> web_sys::HtmlCanvasElement {
     let document = web_sys::window().unwrap().document().unwrap();
     let canvas = document
@@ -43,6 +51,7 @@ fn get_window() -> web_sys::Window {
 async fn start() -> Result<(), JsValue> {
     panic::set_hook(Box::new(|info| {
         // This is added to be able to set a breakpoint in the debugger
+        #[allow(clippy::redundant_locals)]
         let info = info;
         console::error!("{}", info);
     }));
@@ -54,80 +63,53 @@ async fn start() -> Result<(), JsValue> {
         .get_context("webgl2")?
         .unwrap()
         .dyn_into::<WebGl2RenderingContext>()?;
-
-    console::log!("Compiling vertex shader...");
-        let vert_shader = compile_shader(
-            &context,
-            WebGl2RenderingContext::VERTEX_SHADER,
-            get_asset("shaders/triangle.vert").await.as_string().unwrap().as_str()
-        )?;
-
-    console::log!("Compiling fragment shader...");
-        let frag_shader = compile_shader(
-            &context,
-            WebGl2RenderingContext::FRAGMENT_SHADER,
-            get_asset("shaders/triangle.frag").await.as_string().unwrap().as_str()
-        )?;
-
-    console::log!("Linking program...");
-    let program = link_program(&context, &vert_shader, &frag_shader)?;
-    context.use_program(Some(&program));
+    let backend: Rc<dyn RenderBackend> = Rc::new(WebGlBackend::new(context));
+
+    console::log!("Loading assets...");
+    let assets = AssetManager::new(backend.clone());
+    let vert_source = assets.load::<String>("shaders/triangle.vert");
+    let frag_source = assets.load::<String>("shaders/triangle.frag");
+    assets.finish_loading().await?;
+
+    console::log!("Compiling shaders...");
+    let program = ShaderProgram::new(
+        &backend,
+        vert_source.get().ok_or("Vertex shader not loaded")?.as_str(),
+        frag_source.get().ok_or("Fragment shader not loaded")?.as_str()
+    )?;
+    let program = Rc::new(program);
 
     console::log!("Shader initialization complete!");
 
-    let vertices: [f32; 9] = [-0.7, -0.7, 0.0, 0.7, -0.7, 0.0, 0.0, 0.7, 0.0];
-
-    let position_attribute_location = context.get_attrib_location(&program, "position");
-    let buffer = context.create_buffer().ok_or("Failed to create buffer")?;
-    context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
-
-    // Note that `Float32Array::view` is somewhat dangerous (hence the
-    // `unsafe`!). This is creating a raw view into our module's
-    // `WebAssembly.Memory` buffer, but if we allocate more pages for ourself
-    // (aka do a memory allocation in Rust) it'll cause the buffer to change,
-    // causing the `Float32Array` to be invalid.
-    //
-    // As a result, after `Float32Array::view` we have to be very careful not to
-    // do any memory allocations before it's dropped.
-    unsafe {
-        let positions_array_buf_view = js_sys::Float32Array::view(&vertices);
-
-        context.buffer_data_with_array_buffer_view(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            &positions_array_buf_view,
-            WebGl2RenderingContext::STATIC_DRAW,
-        );
-
-        drop(positions_array_buf_view);
-    }
-
-    console::log!("Binding vertex array object...");
-    let vao = context
-        .create_vertex_array()
-        .ok_or("Could not create vertex array object")?;
-    context.bind_vertex_array(Some(&vao));
-
-    context.vertex_attrib_pointer_with_i32(
-        position_attribute_location as u32,
-        3,
-        WebGl2RenderingContext::FLOAT,
-        false,
-        0,
-        0,
-    );
-    context.enable_vertex_attrib_array(position_attribute_location as u32);
-
-    context.bind_vertex_array(Some(&vao));
+    let vertices = vec![-0.7, -0.7, 0.0, 0.7, -0.7, 0.0, 0.0, 0.7, 0.0];
+    let layout = VertexLayout::new().with_attribute(0, 3);
+    let triangle_mesh = Mesh::new(vertices, None, layout);
+    let mut triangle_material = Material::new(program.clone());
+    triangle_material.set_vec4("u_color", glm::vec4(1.0, 0.5, 0.2, 1.0));
 
     console::log!("Dispatching render loop...");
     let time = window().unwrap().performance().unwrap().now() as f32 / 1000.0;
-    let mut state = AppState::new(context, time);
+    let mut state = AppState::new(backend, time);
+    // components load through the same manager, the shaders above are released with their handles
+    state.assets = assets;
 
     state.add_object_empy().borrow_mut().add_component(BasicBackground::new());
 
-    let triangle = GameObject::new();
+    let camera = state.add_object(GameObject::new_named("Camera"));
+    camera.borrow_mut().add_component(Camera::perspective(60.0_f32.to_radians(), 0.1, 100.0));
+    camera.borrow_mut().set_local_position(glm::vec3(0.0, 0.0, 2.0));
+
+    let triangle = GameObject::new_named("Triangle");
     triangle.borrow_mut().set_enabled(true);
-    state.add_object(triangle).borrow_mut().add_component(BasicTriangle::new());
+    state.add_object(triangle).borrow_mut().add_component(MeshRenderer::new(triangle_mesh, triangle_material));
+
+    let mut gameplay = InputContext::new();
+    gameplay.bind_action("jump", ActionBinding::Key(Key::Space));
+    gameplay.bind_axis("move_x", AxisBinding::Keys { negative: Key::A, positive: Key::D });
+    gameplay.bind_axis("move_x", AxisBinding::Keys { negative: Key::ArrowLeft, positive: Key::ArrowRight });
+    gameplay.bind_axis("look_x", AxisBinding::MouseDelta { axis: MouseAxis::X, scale: 0.1 });
+    state.input.add_context("gameplay", gameplay);
+    state.input.set_context("gameplay");
 
     console::log!("Registering callbacks...");
 
@@ -140,7 +122,7 @@ async fn start() -> Result<(), JsValue> {
     Ok(())
 }
 
-fn on_canvas_resize(old_width: u32, old_height: u32, new_width: u32, new_height: u32) {
+fn on_canvas_resize(state: &mut AppState, old_width: u32, old_height: u32, new_width: u32, new_height: u32) {
     console::log!(
         "Resizing from {}x{} to {}x{}",
         old_width,
@@ -148,6 +130,8 @@ fn on_canvas_resize(old_width: u32, old_height: u32, new_width: u32, new_height:
         new_width,
         new_height
     );
+
+    state.on_canvas_resize(new_width, new_height);
 }
 
 fn draw(state: &mut AppState, delta_time: f32) {
@@ -156,41 +140,110 @@ fn draw(state: &mut AppState, delta_time: f32) {
     state.draw();
 }
 
+fn log_touch(touch: &Touch) {
+    if touch.started_this_frame {
+        console::log!("Touch {} started at {}, {}", touch.id, touch.start_position.0, touch.start_position.1)
+    }
+    match touch.phase {
+        TouchPhase::Ended => console::log!("Touch {} ended at {}, {}", touch.id, touch.position.0, touch.position.1),
+        TouchPhase::Cancelled => console::log!("Touch {} cancelled", touch.id),
+        _ => {}
+    }
+}
+
 #[allow(static_mut_refs)]
 fn render_loop_event(mut state: AppState) {
     let canvas = get_canvas();
-    if canvas.client_width() as u32 != canvas.width()
-        || canvas.client_height() as u32 != canvas.height()
+    // The drawing buffer matches physical pixels, so the image stays sharp on high-DPI screens
+    let pixel_ratio = get_window().device_pixel_ratio();
+    let client_width = (canvas.client_width() as f64 * pixel_ratio).round() as u32;
+    let client_height = (canvas.client_height() as f64 * pixel_ratio).round() as u32;
+    if client_width != canvas.width()
+        || client_height != canvas.height()
     {
         let old_width = canvas.width();
         let old_height = canvas.height();
-        let new_width = canvas.client_width() as u32;
-        let new_height = canvas.client_height() as u32;
+        let new_width = client_width;
+        let new_height = client_height;
         canvas.set_width(new_width);
         canvas.set_height(new_height);
-        on_canvas_resize(old_width, old_height, new_width, new_height);
+        on_canvas_resize(&mut state, old_width, old_height, new_width, new_height);
     }
 
     state.process_events();
 
-    if state.keyboard.was_key_pressed(65 /* A */) {
+    if state.keyboard.was_key_pressed(Key::A) {
         console::log!("A key pressed");
     }
-    if state.mouse.was_button_pressed(0) {
+    if state.input.was_action_pressed("jump") {
+        console::log!("Jump action")
+    }
+    if state.input.get_axis("move_x") != 0.0 {
+        console::log!("Move axis: {}", state.input.get_axis("move_x"))
+    }
+    if state.mouse.was_button_pressed(MouseButton::Left) {
         console::log!("LMB pressed")
     }
-    if state.mouse.was_button_pressed(1) {
+    if state.mouse.was_button_pressed(MouseButton::Middle) {
         console::log!("MMB pressed")
     }
-    if state.mouse.was_button_pressed(2) {
+    if state.mouse.was_button_pressed(MouseButton::Right) {
         console::log!("RMB pressed")
     }
-    if state.mouse.mouse_delta.0 != 0 || state.mouse.mouse_delta.1 != 0 {
-        console::log!("Mouse position: {}, {}", state.mouse.position.0, state.mouse.position.1)
+    if state.mouse.mouse_delta.0 != 0.0 || state.mouse.mouse_delta.1 != 0.0 {
+        console::log!(
+            "Mouse position: {}, {} (NDC: {}, {})",
+            state.mouse.position.0,
+            state.mouse.position.1,
+            state.mouse.position_ndc.0,
+            state.mouse.position_ndc.1
+        )
+    }
+    for touch in state.touch.get_touches() {
+        log_touch(touch);
+    }
+    if let Some(PinchGesture { center, scale }) = state.touch.pinch {
+        console::log!("Pinch: {} at {}, {}", scale, center.0, center.1)
+    }
+    if let Some(PanGesture { center, delta }) = state.touch.pan {
+        console::log!("Pan: {}, {} at {}, {}", delta.0, delta.1, center.0, center.1)
+    }
+    for pad in state.gamepad.get_connected_gamepads() {
+        if state.gamepad.was_connected(pad) {
+            console::log!("Gamepad {} connected", pad)
+        }
+        if state.gamepad.was_button_pressed(pad, GamepadButton::South) {
+            console::log!("Gamepad {} south button pressed", pad)
+        }
+        if state.gamepad.get_axis(pad, GamepadAxis::LeftStickX) != 0.0 {
+            console::log!("Gamepad {} left stick x: {}", pad, state.gamepad.get_axis(pad, GamepadAxis::LeftStickX))
+        }
     }
     if state.mouse.scroll_delta != 0.0 {
         console::log!("Scroll delta: {}", state.mouse.scroll_delta) 
     }
+    // Enter toggles typing, everything typed meanwhile is logged
+    if state.keyboard.was_key_pressed(Key::Enter) {
+        if state.is_text_input_active() {
+            state.stop_text_input();
+        } else {
+            state.start_text_input();
+        }
+    }
+    // P pauses, N steps a single frame while paused
+    if state.keyboard.was_key_pressed(Key::P) && !state.is_text_input_active() {
+        if state.time.is_paused() {
+            state.time.resume();
+        } else {
+            state.time.pause();
+        }
+    }
+    if state.keyboard.was_key_pressed(Key::N) && !state.is_text_input_active() {
+        state.time.step_frame();
+    }
+    if !state.keyboard.get_text().is_empty() {
+        console::log!("Text: {}", state.keyboard.get_text())
+    }
 
     let time = window().unwrap().performance().unwrap().now() as f32 / 1000.0;
     state.update(time);
@@ -210,53 +263,3 @@ fn render_loop_event(mut state: AppState) {
         WINDOW_ANIMATION_FRAME_REQUEST_CLOSURE.replace(a);
     }
 }
-
-pub fn compile_shader(
-    context: &WebGl2RenderingContext,
-    shader_type: u32,
-    source: &str,
-) -> Result<WebGlShader, String> {
-    let shader = context
-        .create_shader(shader_type)
-        .ok_or_else(|| String::from("Unable to create shader object"))?;
-    context.shader_source(&shader, source);
-    context.compile_shader(&shader);
-
-    if context
-        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        Ok(shader)
-    } else {
-        Err(context
-            .get_shader_info_log(&shader)
-            .unwrap_or_else(|| String::from("Unknown error creating shader")))
-    }
-}
-
-pub fn link_program(
-    context: &WebGl2RenderingContext,
-    vert_shader: &WebGlShader,
-    frag_shader: &WebGlShader,
-) -> Result<WebGlProgram, String> {
-    let program = context
-        .create_program()
-        .ok_or_else(|| String::from("Unable to create shader object"))?;
-
-    context.attach_shader(&program, vert_shader);
-    context.attach_shader(&program, frag_shader);
-    context.link_program(&program);
-
-    if context
-        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        Ok(program)
-    } else {
-        Err(context
-            .get_program_info_log(&program)
-            .unwrap_or_else(|| String::from("Unknown error creating program object")))
-    }
-}
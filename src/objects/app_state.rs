@@ -1,37 +1,161 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsCast;
-use web_sys::{HtmlCanvasElement, KeyboardEvent, MouseEvent, WebGl2RenderingContext, WheelEvent};
+use web_sys::{CompositionEvent, EventTarget, FocusEvent, Gamepad, HtmlCanvasElement, HtmlInputElement, KeyboardEvent, MouseEvent, TouchEvent, WheelEvent};
 
+use crate::assets::AssetManager;
+use crate::console;
 use crate::objects::game_object::GameObject;
 use crate::renderer::gl_render::GLRender;
+use crate::renderer::render_backend::RenderBackend;
 
-use crate::input::{InputEvent, KeyboardState, KeyboardStateSnapshot, MouseState, MouseStateSnapshot};
+use crate::input::{ButtonData, GamepadState, GamepadStateSnapshot, InputEvent, InputMap, InputRecorder, InputRecording, InputReplay, KeyboardInput, KeyboardState, KeyboardStateSnapshot, MouseButton, MouseState, MouseStateSnapshot, TouchState, TouchStateSnapshot};
 
 pub struct Time {
-    start_time: f32,
     last_time: f32,
+    // Scaled by `time_scale`, 0 while the time scale is 0
     pub delta_time: f32,
     pub elapsed_time: f32,
+    // Real time between frames, clamped to `max_delta_time`. Meant for UI and debug tools.
+    pub unscaled_delta_time: f32,
+    pub unscaled_elapsed_time: f32,
+    // Frames the scene was updated in, frames skipped while paused don't count
+    pub frame_count: u64,
+    // Step of `fixed_update`, components should use it there instead of `delta_time`
+    pub fixed_delta_time: f32,
+    // How far between the last and the next fixed step the frame is, 0..1.
+    // Lets rendering interpolate state that only changes in `fixed_update`.
+    pub interpolation_alpha: f32,
+    fixed_accumulator: f32,
+    max_fixed_steps: u32,
+    time_scale: f32,
+    max_delta_time: f32,
+    is_paused: bool,
+    is_step_requested: bool,
+    is_frame_running: bool,
 }
 
+#[allow(dead_code)]
 impl Time {
     pub fn new(now: f32) -> Self {
         Self {
-            start_time: now,
-            last_time: 0.0,
+            last_time: now,
             delta_time: 0.0,
             elapsed_time: 0.0,
+            unscaled_delta_time: 0.0,
+            unscaled_elapsed_time: 0.0,
+            frame_count: 0,
+            fixed_delta_time: 1.0 / 60.0,
+            interpolation_alpha: 0.0,
+            fixed_accumulator: 0.0,
+            max_fixed_steps: 5,
+            time_scale: 1.0,
+            max_delta_time: 0.25,
+            is_paused: false,
+            is_step_requested: false,
+            is_frame_running: true,
         }
     }
 
+    // 1 is real time, 0.5 slow motion, 0 stops gameplay while updates keep running
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn get_time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    // Longest frame the scene is advanced by, e.g. after the tab was in the background
+    pub fn set_max_delta_time(&mut self, max_delta_time: f32) {
+        if max_delta_time > 0.0 {
+            self.max_delta_time = max_delta_time;
+        }
+    }
+
+    // A debug pause, unlike a time scale of 0 no component is updated at all.
+    // Input and drawing keep going.
+    pub fn pause(&mut self) {
+        self.is_paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.is_paused = false;
+        self.is_step_requested = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    // While paused, lets exactly one frame run the next time `AppState::update` is called
+    pub fn step_frame(&mut self) {
+        if self.is_paused {
+            self.is_step_requested = true;
+        }
+    }
+
+    // Whether the scene is updated in the current frame
+    pub fn is_frame_running(&self) -> bool {
+        self.is_frame_running
+    }
+
+    pub fn set_fixed_delta_time(&mut self, step: f32) {
+        if step > 0.0 {
+            self.fixed_delta_time = step;
+        }
+    }
+
+    // Caps the fixed steps run in one frame. When fixed updates take longer than the time
+    // they simulate, catching up would only make the next frame longer still.
+    pub fn set_max_fixed_steps(&mut self, steps: u32) {
+        self.max_fixed_steps = steps.max(1);
+    }
+
+    // Adds the frame time to the accumulator and returns how many fixed steps to run
+    pub fn take_fixed_steps(&mut self) -> u32 {
+        self.fixed_accumulator += self.delta_time.max(0.0);
+
+        let due_steps = (self.fixed_accumulator / self.fixed_delta_time) as u32;
+        let steps = due_steps.min(self.max_fixed_steps);
+        self.fixed_accumulator -= steps as f32 * self.fixed_delta_time;
+        if due_steps > steps {
+            // the time that couldn't be simulated is dropped, the game slows down instead
+            self.fixed_accumulator = self.fixed_accumulator.min(self.fixed_delta_time);
+        }
+
+        self.interpolation_alpha = (self.fixed_accumulator / self.fixed_delta_time).clamp(0.0, 1.0);
+        steps
+    }
+
     pub fn update(&mut self, time: f32) {
-        self.delta_time = time - self.last_time;
-        self.elapsed_time = time - self.start_time;
+        let is_frame_running = !self.is_paused || std::mem::take(&mut self.is_step_requested);
+        self.advance(time, time - self.last_time, is_frame_running);
+    }
+
+    // Restores a recorded frame instead of measuring it, pausing included
+    pub fn set_recorded(&mut self, time: f32, unscaled_delta_time: f32, is_frame_running: bool) {
+        self.advance(time, unscaled_delta_time, is_frame_running);
+    }
+
+    fn advance(&mut self, time: f32, unscaled_delta_time: f32, is_frame_running: bool) {
         self.last_time = time;
+        self.is_frame_running = is_frame_running;
+        self.unscaled_delta_time = unscaled_delta_time.clamp(0.0, self.max_delta_time);
+        self.unscaled_elapsed_time += self.unscaled_delta_time;
+
+        if !is_frame_running {
+            self.delta_time = 0.0;
+            return;
+        }
+
+        self.delta_time = self.unscaled_delta_time * self.time_scale;
+        self.elapsed_time += self.delta_time;
+        self.frame_count += 1;
     }
 }
 
@@ -40,32 +164,64 @@ pub struct AppState {
     events: Arc<Mutex<Vec<InputEvent>>>,
     renderer: GLRender,
     root_object: Rc<RefCell<GameObject>>,
+    canvas: Option<HtmlCanvasElement>,
+    text_input: Option<HtmlInputElement>,
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
+    // events of the current frame, kept until `update` knows the frame time
+    recorded_events: Vec<InputEvent>,
+    replayed_time: Option<(f32, f32, bool)>,
+    canvas_size: (u32, u32),
     keyboard_state: KeyboardState,
     mouse_state: MouseState,
+    touch_state: TouchState,
+    gamepad_state: GamepadState,
     pub keyboard: KeyboardStateSnapshot,
-    pub mouse: MouseStateSnapshot
+    pub mouse: MouseStateSnapshot,
+    pub touch: TouchStateSnapshot,
+    pub gamepad: GamepadStateSnapshot,
+    pub input: InputMap,
+    pub assets: AssetManager
 }
 
+#[allow(dead_code)]
 impl AppState {
-    pub fn new(context: WebGl2RenderingContext, cur_time: f32) -> Self {
-        let root_node = GameObject::new();
+    pub fn new(backend: Rc<dyn RenderBackend>, cur_time: f32) -> Self {
+        let root_node = GameObject::new_named("Root");
         root_node.borrow_mut().set_as_root_node(root_node.clone());
 
         let keyboard_state = KeyboardState::new();
         let keyboard_state_snapshot = KeyboardStateSnapshot::from(&keyboard_state);
 
-        let mouse_state = MouseState::new();
+        let (width, height) = backend.get_drawing_buffer_size();
+        let canvas_size = (width as u32, height as u32);
+
+        let mut mouse_state = MouseState::new();
+        mouse_state.set_canvas_size(canvas_size.0, canvas_size.1);
         let mouse_state_snapshot = MouseStateSnapshot::from(&mouse_state);
 
         Self {
             events: Arc::from(Mutex::from(Vec::new())),
-            renderer: GLRender::new(context),
+            renderer: GLRender::new(backend.clone()),
+            canvas: None,
+            text_input: None,
+            recorder: None,
+            replay: None,
+            recorded_events: Vec::new(),
+            replayed_time: None,
+            canvas_size,
             time: Time::new(cur_time),
             root_object: root_node,
             keyboard_state,
-            mouse_state: MouseState::new(),
+            mouse_state,
+            touch_state: TouchState::new(),
+            gamepad_state: GamepadState::new(),
             keyboard: keyboard_state_snapshot,
-            mouse: mouse_state_snapshot
+            mouse: mouse_state_snapshot,
+            touch: TouchStateSnapshot::new(),
+            gamepad: GamepadStateSnapshot::new(),
+            input: InputMap::new(),
+            assets: AssetManager::new(backend)
         }
     }
 
@@ -79,22 +235,156 @@ impl AppState {
         new_child
     }
 
+    // Scene queries, the root object itself is never part of the results.
+    // Paths start below the root, e.g. "Level/Enemies/Boss".
+    pub fn find_by_name(&self, name: &str) -> Option<Rc<RefCell<GameObject>>> {
+        self.iter_depth_first().find(|object| object.borrow().get_name() == name)
+    }
+
+    pub fn find_by_path(&self, path: &str) -> Option<Rc<RefCell<GameObject>>> {
+        self.root_object.borrow().find_by_path(path)
+    }
+
+    pub fn find_all_with_tag(&self, tag: &str) -> Vec<Rc<RefCell<GameObject>>> {
+        self.iter_depth_first().filter(|object| object.borrow().has_tag(tag)).collect()
+    }
+
+    pub fn iter_depth_first(&self) -> impl Iterator<Item = Rc<RefCell<GameObject>>> {
+        GameObject::iter_depth_first(&self.root_object).skip(1)
+    }
+
+    pub fn iter_breadth_first(&self) -> impl Iterator<Item = Rc<RefCell<GameObject>>> {
+        GameObject::iter_breadth_first(&self.root_object).skip(1)
+    }
+
     pub fn update(&mut self, cur_time: f32) {
-        self.time.update(cur_time);
-        let mut object = self.root_object.borrow_mut();
-        // Start the object if it hasn't been started
-        // Start is called only once and this is handled by GameObject internals.
-        object.start(&self);
-        object.update(&self);
+        // while replaying, the recorded time is applied instead of the wall-clock one
+        let applied_time = match self.replayed_time.take() {
+            Some((time, delta_time, is_frame_running)) => {
+                self.time.set_recorded(time, delta_time, is_frame_running);
+                time
+            }
+            None => {
+                self.time.update(cur_time);
+                cur_time
+            }
+        };
+
+        if let Some(recorder) = &mut self.recorder {
+            let events = std::mem::take(&mut self.recorded_events);
+            recorder.record_frame(applied_time, self.time.unscaled_delta_time, self.time.is_frame_running(), events);
+        }
+
+        // paused, the scene stays exactly as it is
+        if !self.time.is_frame_running() {
+            return;
+        }
+
+        let root_object = self.root_object.clone();
+        // Enable, disable and start notifications come before any update,
+        // start is called only once and this is handled by GameObject internals.
+        GameObject::update_active_state(&root_object, true, self);
+        for _ in 0..self.time.take_fixed_steps() {
+            GameObject::fixed_update(&root_object, self);
+        }
+        GameObject::update(&root_object, self);
+        GameObject::late_update(&root_object, self);
+        // Destruction is deferred until now, so nothing disappears in the middle of an update
+        GameObject::remove_destroyed(&root_object, self);
+    }
+
+    // Every frame from now on is recorded with its input events and time values
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(InputRecorder::new());
+    }
+
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recorder.take().map(|recorder| recorder.finish())
+    }
+
+    // Feeds the recording through `process_events` and `update` instead of live input,
+    // starting with the next frame. Live input resumes once the recording runs out.
+    pub fn start_replay(&mut self, recording: InputRecording) {
+        self.replay = Some(InputReplay::new(recording));
+    }
+
+    pub fn stop_replay(&mut self) {
+        self.replay = None;
+        self.replayed_time = None;
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    // Stick and axis values within the deadzone read as 0, it is 0.15 by default
+    pub fn set_gamepad_deadzone(&mut self, deadzone: f32) {
+        self.gamepad_state.set_deadzone(deadzone);
+        self.gamepad = self.gamepad_state.snapshot();
+    }
+
+    pub fn get_canvas_size(&self) -> (u32, u32) {
+        self.canvas_size
+    }
+
+    pub fn on_canvas_resize(&mut self, width: u32, height: u32) {
+        self.canvas_size = (width, height);
+        self.mouse_state.set_canvas_size(width, height);
+        self.root_object.borrow().on_canvas_resize(width, height);
+    }
+
+    // Has to be called shortly after a user gesture (e.g. a click), browsers ignore it otherwise.
+    // The result arrives as a lock state change in `mouse.is_pointer_locked`.
+    pub fn request_pointer_lock(&self) {
+        if let Some(canvas) = &self.canvas {
+            canvas.request_pointer_lock();
+        }
+    }
+
+    pub fn exit_pointer_lock(&self) {
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            document.exit_pointer_lock();
+        }
+    }
+
+    // Moves the keyboard focus to a hidden input element, so the browser delivers typed text
+    // and IME composition. Key events keep coming as usual and held keys stay held.
+    pub fn start_text_input(&self) {
+        if let Some(text_input) = &self.text_input {
+            let _ = text_input.focus();
+        }
+    }
+
+    pub fn stop_text_input(&self) {
+        if let Some(text_input) = &self.text_input {
+            let _ = text_input.blur();
+        }
+        if let Some(canvas) = &self.canvas {
+            let _ = canvas.focus();
+        }
+    }
+
+    pub fn is_text_input_active(&self) -> bool {
+        let Some(text_input) = &self.text_input else {
+            return false;
+        };
+        let document = web_sys::window().and_then(|window| window.document());
+        let active_element = document.and_then(|document| document.active_element());
+        active_element.is_some_and(|element| element == ***text_input)
     }
 
     pub fn setup_callbacks(&mut self, canvas: &HtmlCanvasElement) {
+        self.canvas = Some(canvas.clone());
+
         /* mouse move */
         {
             let events = self.events.clone();
+            let target = canvas.clone();
             let closure: Box<dyn FnMut(MouseEvent)> = Box::new(move |e: MouseEvent| {
+                let (x, y) = canvas_position(&target, e.client_x(), e.client_y());
                 let mut events = events.lock().unwrap();
-                events.push(InputEvent::MouseMoved(e.screen_x(), e.screen_y()));
+                events.push(InputEvent::MouseMoved(x, y));
+                events.push(InputEvent::MouseRawMoved(e.movement_x() as f32, e.movement_y() as f32));
             });
 
             let closure = Closure::wrap(closure);
@@ -107,9 +397,11 @@ impl AppState {
         /* mouse click */
         {
             let events = self.events.clone();
+            let target = canvas.clone();
             let closure: Box<dyn FnMut(MouseEvent)> = Box::new(move |e: MouseEvent| {
+                let (x, y) = canvas_position(&target, e.client_x(), e.client_y());
                 let mut events = events.lock().unwrap();
-                events.push(InputEvent::MouseKeyPressed(e.screen_x(), e.screen_y(), e.button()));
+                events.push(InputEvent::MouseKeyPressed(x, y, MouseButton::from_button(e.button())));
             });
 
             let closure = Closure::wrap(closure);
@@ -122,9 +414,11 @@ impl AppState {
         /* mouse click release */
         {
             let events = self.events.clone();
+            let target = canvas.clone();
             let closure: Box<dyn FnMut(MouseEvent)> = Box::new(move |e: MouseEvent| {
+                let (x, y) = canvas_position(&target, e.client_x(), e.client_y());
                 let mut events = events.lock().unwrap();
-                events.push(InputEvent::MouseKeyReleased(e.screen_x(), e.screen_y(), e.button()));
+                events.push(InputEvent::MouseKeyReleased(x, y, MouseButton::from_button(e.button())));
                 // this will allow using mouse buttons that normally navigate through tab history
                 e.prevent_default();
             });
@@ -151,16 +445,214 @@ impl AppState {
             closure.forget();
         }
 
+        /* touch */
+        {
+            let closure = touch_closure(self.events.clone(), canvas.clone(), InputEvent::TouchStarted);
+            canvas.set_ontouchstart(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+
+            let closure = touch_closure(self.events.clone(), canvas.clone(), InputEvent::TouchMoved);
+            canvas.set_ontouchmove(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+
+            let closure = touch_closure(self.events.clone(), canvas.clone(), InputEvent::TouchEnded);
+            canvas.set_ontouchend(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+
+            let closure = touch_closure(self.events.clone(), canvas.clone(), |id, _, _| InputEvent::TouchCancelled(id));
+            canvas.set_ontouchcancel(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+        }
+
+        /* key down */
+        {
+            let closure = key_closure(self.events.clone(), InputEvent::KeyPressed);
+            canvas.set_onkeydown(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+        }
+
+        /* key up */
+        {
+            let closure = key_closure(self.events.clone(), InputEvent::KeyReleased);
+            canvas.set_onkeyup(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+        }
+
+        /* text input */
+        match self.create_text_input() {
+            Ok(text_input) => self.text_input = Some(text_input),
+            Err(err) => console::error!("Failed to create the text input element: {}", err),
+        }
+
+        /* pointer lock */
+        {
+            let events = self.events.clone();
+            let target = canvas.clone();
+            let document = web_sys::window().unwrap().document().unwrap();
+            let closure: Box<dyn FnMut()> = Box::new(move || {
+                let document = web_sys::window().unwrap().document().unwrap();
+                let locked_element = document.pointer_lock_element();
+                let is_locked = locked_element.is_some_and(|element| element == **target);
+                let mut events = events.lock().unwrap();
+                events.push(InputEvent::PointerLockChanged(is_locked));
+            });
+
+            let closure = Closure::wrap(closure);
+
+            document.set_onpointerlockchange(Some(closure.as_ref().unchecked_ref()));
+
+            closure.forget();
+        }
+
+        /* focus loss */
+        {
+            // moving the focus to the text input keeps the keyboard, see `start_text_input`
+            let text_input = self.text_input.clone().map(EventTarget::from);
+            let closure = focus_lost_closure(self.events.clone(), text_input);
+
+            canvas.set_onblur(Some(closure.as_ref().unchecked_ref()));
+
+            closure.forget();
+        }
+
+    }
+
+    // The hidden element receiving text while text input is active.
+    // It has to stay focusable, so it's transparent instead of `display: none`.
+    fn create_text_input(&self) -> Result<HtmlInputElement, String> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or("No document")?;
+        let body = document.body().ok_or("No document body")?;
+
+        let text_input = document
+            .create_element("input")
+            .map_err(|_| "Failed to create the input element")?
+            .dyn_into::<HtmlInputElement>()
+            .map_err(|_| "Created element is not an input")?;
+        text_input.set_type("text");
+        let _ = text_input.set_attribute("autocomplete", "off");
+        let _ = text_input.set_attribute("autocapitalize", "off");
+        let _ = text_input.set_attribute("spellcheck", "false");
+        let _ = text_input.set_attribute(
+            "style",
+            "position: fixed; left: 0px; top: 0px; width: 1px; height: 1px; opacity: 0; pointer-events: none;",
+        );
+        body.append_child(&text_input).map_err(|_| "Failed to attach the input element")?;
+
+        /* committed text */
+        {
+            let events = self.events.clone();
+            let target = text_input.clone();
+            let closure: Box<dyn FnMut(web_sys::InputEvent)> = Box::new(move |e: web_sys::InputEvent| {
+                // composed text is reported by compositionend instead
+                if e.is_composing() {
+                    return;
+                }
+                // the element only buffers text between events, so it's emptied each time
+                let text = target.value();
+                target.set_value("");
+                if text.is_empty() {
+                    return;
+                }
+                let mut events = events.lock().unwrap();
+                events.push(InputEvent::TextEntered(text));
+            });
+
+            let closure = Closure::wrap(closure);
+
+            text_input.set_oninput(Some(closure.as_ref().unchecked_ref()));
+
+            closure.forget();
+        }
+
+        /* composition */
+        {
+            let events = self.events.clone();
+            let closure: Box<dyn FnMut(CompositionEvent)> = Box::new(move |_: CompositionEvent| {
+                let mut events = events.lock().unwrap();
+                events.push(InputEvent::CompositionStarted);
+            });
+            let closure = Closure::wrap(closure);
+            let _ = text_input.add_event_listener_with_callback("compositionstart", closure.as_ref().unchecked_ref());
+            closure.forget();
+
+            let events = self.events.clone();
+            let closure: Box<dyn FnMut(CompositionEvent)> = Box::new(move |e: CompositionEvent| {
+                let mut events = events.lock().unwrap();
+                events.push(InputEvent::CompositionUpdated(e.data().unwrap_or_default()));
+            });
+            let closure = Closure::wrap(closure);
+            let _ = text_input.add_event_listener_with_callback("compositionupdate", closure.as_ref().unchecked_ref());
+            closure.forget();
+
+            let events = self.events.clone();
+            let target = text_input.clone();
+            let closure: Box<dyn FnMut(CompositionEvent)> = Box::new(move |e: CompositionEvent| {
+                // some browsers follow with a non-composing input event, it finds the element empty
+                target.set_value("");
+                let mut events = events.lock().unwrap();
+                events.push(InputEvent::CompositionEnded(e.data().unwrap_or_default()));
+            });
+            let closure = Closure::wrap(closure);
+            let _ = text_input.add_event_listener_with_callback("compositionend", closure.as_ref().unchecked_ref());
+            closure.forget();
+        }
+
+        /* keys, focus loss */
+        {
+            let closure = key_closure(self.events.clone(), InputEvent::KeyPressed);
+            text_input.set_onkeydown(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+
+            let closure = key_closure(self.events.clone(), InputEvent::KeyReleased);
+            text_input.set_onkeyup(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+
+            // and so does moving it back, see `stop_text_input`
+            let canvas = self.canvas.clone().map(EventTarget::from);
+            let closure = focus_lost_closure(self.events.clone(), canvas);
+            text_input.set_onblur(Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+        }
+
+        Ok(text_input)
     }
 
     pub fn process_events(&mut self) {
         let mut events = self.events.lock().unwrap();
 
+        if let Some(replay) = &mut self.replay {
+            match replay.next_frame() {
+                Some(frame) => {
+                    // live input is ignored while replaying, the recorded frame takes its place
+                    events.clear();
+                    events.extend(frame.events);
+                    self.replayed_time = Some((frame.time, frame.unscaled_delta_time, frame.is_frame_running));
+                }
+                None => self.replay = None,
+            }
+        }
+
+        if self.replay.is_none() {
+            // gamepads have no events for buttons and axes, they are polled once per frame instead
+            self.gamepad_state.diff_events(&poll_gamepads(), &mut events);
+        }
+
+        if self.recorder.is_some() {
+            self.recorded_events = events.clone();
+        }
+
         self.keyboard_state.process_events(&events);
         self.mouse_state.process_events(&events);
+        self.touch_state.process_events(&events);
+        self.gamepad_state.process_events(&events);
 
         self.keyboard = self.keyboard_state.snapshot();
         self.mouse = self.mouse_state.snapshot();
+        self.touch = self.touch_state.snapshot();
+        self.gamepad = self.gamepad_state.snapshot();
+        self.input.update(&self.keyboard, &self.mouse);
 
         events.clear();
     }
@@ -172,3 +664,349 @@ impl AppState {
         self.renderer.render(self, &self.root_object);
     }
 }
+
+// Converts the event position to canvas pixels, i.e. the same units as the drawing buffer.
+// The ratio between the canvas resolution and its CSS size covers both
+// `devicePixelRatio` and any CSS scaling of the canvas element.
+fn canvas_position(canvas: &HtmlCanvasElement, client_x: i32, client_y: i32) -> (f32, f32) {
+    let rect = canvas.get_bounding_client_rect();
+    let scale_x = if rect.width() > 0.0 { canvas.width() as f64 / rect.width() } else { 1.0 };
+    let scale_y = if rect.height() > 0.0 { canvas.height() as f64 / rect.height() } else { 1.0 };
+
+    (
+        ((client_x as f64 - rect.left()) * scale_x) as f32,
+        ((client_y as f64 - rect.top()) * scale_y) as f32,
+    )
+}
+
+fn key_closure(
+    events: Arc<Mutex<Vec<InputEvent>>>,
+    to_event: fn(KeyboardInput) -> InputEvent,
+) -> Closure<dyn FnMut(KeyboardEvent)> {
+    let closure: Box<dyn FnMut(KeyboardEvent)> = Box::new(move |e: KeyboardEvent| {
+        let mut events = events.lock().unwrap();
+        events.push(to_event(KeyboardInput::from(&e)));
+    });
+
+    Closure::wrap(closure)
+}
+
+// Keys held while the focus leaves never get released, unless the focus goes to `keeps_focus`
+fn focus_lost_closure(events: Arc<Mutex<Vec<InputEvent>>>, keeps_focus: Option<EventTarget>) -> Closure<dyn FnMut(FocusEvent)> {
+    let closure: Box<dyn FnMut(FocusEvent)> = Box::new(move |e: FocusEvent| {
+        if keeps_focus.is_some() && e.related_target() == keeps_focus {
+            return;
+        }
+        let mut events = events.lock().unwrap();
+        events.push(InputEvent::FocusLost);
+    });
+
+    Closure::wrap(closure)
+}
+
+// Touch events can carry several changed touches at once, each becomes a separate input event
+fn touch_closure(
+    events: Arc<Mutex<Vec<InputEvent>>>,
+    canvas: HtmlCanvasElement,
+    to_event: fn(i32, f32, f32) -> InputEvent,
+) -> Closure<dyn FnMut(TouchEvent)> {
+    let closure: Box<dyn FnMut(TouchEvent)> = Box::new(move |e: TouchEvent| {
+        // keeps the browser from scrolling, zooming and emulating mouse events
+        e.prevent_default();
+        let touches = e.changed_touches();
+        let mut events = events.lock().unwrap();
+        for index in 0..touches.length() {
+            let Some(touch) = touches.get(index) else {
+                continue;
+            };
+            let (x, y) = canvas_position(&canvas, touch.client_x(), touch.client_y());
+            events.push(to_event(touch.identifier(), x, y));
+        }
+    });
+
+    Closure::wrap(closure)
+}
+
+// Reads every connected gamepad from `navigator.getGamepads()`, keyed by the gamepad index
+fn poll_gamepads() -> BTreeMap<u32, (Vec<ButtonData>, Vec<f32>)> {
+    let mut polled = BTreeMap::new();
+    // there's no navigator outside of the browser, e.g. in native tests
+    if !cfg!(target_arch = "wasm32") {
+        return polled;
+    }
+
+    let Some(window) = web_sys::window() else {
+        return polled;
+    };
+    let Ok(gamepads) = window.navigator().get_gamepads() else {
+        return polled;
+    };
+
+    for gamepad in gamepads.iter() {
+        // empty slots are reported as null
+        let Ok(gamepad) = gamepad.dyn_into::<Gamepad>() else {
+            continue;
+        };
+        if !gamepad.connected() {
+            continue;
+        }
+
+        let buttons = gamepad
+            .buttons()
+            .iter()
+            .filter_map(|button| button.dyn_into::<web_sys::GamepadButton>().ok())
+            .map(|button| ButtonData {
+                pressed: button.pressed(),
+                value: button.value() as f32,
+            })
+            .collect();
+        let axes = gamepad
+            .axes()
+            .iter()
+            .map(|axis| axis.as_f64().unwrap_or(0.0) as f32)
+            .collect();
+
+        polled.insert(gamepad.index(), (buttons, axes));
+    }
+
+    polled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Key;
+    use crate::renderer::recording_backend::RecordingBackend;
+
+    fn new_state() -> AppState {
+        AppState::new(Rc::new(RecordingBackend::new(640, 480)), 0.0)
+    }
+
+    fn key(code: Key) -> KeyboardInput {
+        KeyboardInput {
+            code,
+            key: String::new(),
+            modifiers: Default::default(),
+            repeat: false,
+        }
+    }
+
+    // Queues events the way the browser callbacks do, then runs one frame
+    fn run_frame(state: &mut AppState, time: f32, events: Vec<InputEvent>) {
+        state.events.lock().unwrap().extend(events);
+        state.process_events();
+        state.update(time);
+    }
+
+    // Steps and frame times are powers of two, so the accumulator stays exact
+    fn fixed_step_time() -> Time {
+        let mut time = Time::new(0.0);
+        time.set_max_delta_time(1.0);
+        time.set_fixed_delta_time(0.125);
+        time
+    }
+
+    #[test]
+    fn fixed_steps_catch_up_at_most_max_fixed_steps() {
+        let mut time = fixed_step_time();
+        time.set_max_fixed_steps(3);
+
+        time.update(1.0);
+        assert_eq!(time.take_fixed_steps(), 3);
+
+        time.update(1.5);
+        assert_eq!(time.take_fixed_steps(), 3);
+    }
+
+    #[test]
+    fn dropped_fixed_steps_leave_at_most_one_step_behind() {
+        let mut time = fixed_step_time();
+        time.set_max_fixed_steps(3);
+
+        // 8 steps are due, the 5 that don't run are dropped except for one
+        time.update(1.0);
+        assert_eq!(time.take_fixed_steps(), 3);
+        assert_eq!(time.interpolation_alpha, 1.0);
+
+        time.update(1.0625);
+        assert_eq!(time.take_fixed_steps(), 1);
+        assert_eq!(time.interpolation_alpha, 0.5);
+    }
+
+    #[test]
+    fn interpolation_alpha_is_the_leftover_part_of_a_step() {
+        let mut time = fixed_step_time();
+
+        time.update(0.0625);
+        assert_eq!(time.take_fixed_steps(), 0);
+        assert_eq!(time.interpolation_alpha, 0.5);
+
+        time.update(0.15625);
+        assert_eq!(time.take_fixed_steps(), 1);
+        assert_eq!(time.interpolation_alpha, 0.25);
+
+        time.update(0.25);
+        assert_eq!(time.take_fixed_steps(), 1);
+        assert_eq!(time.interpolation_alpha, 0.0);
+    }
+
+    #[test]
+    fn zero_time_scale_runs_no_fixed_steps() {
+        let mut time = fixed_step_time();
+        time.set_time_scale(0.0);
+
+        for frame in 1..=4 {
+            time.update(frame as f32 * 0.5);
+            assert_eq!(time.take_fixed_steps(), 0);
+        }
+        assert_eq!(time.interpolation_alpha, 0.0);
+        assert_eq!(time.elapsed_time, 0.0);
+        assert_eq!(time.unscaled_elapsed_time, 2.0);
+        assert_eq!(time.frame_count, 4);
+    }
+
+    #[test]
+    fn step_frame_advances_exactly_one_paused_frame() {
+        let mut time = Time::new(0.0);
+        time.update(0.125);
+        time.pause();
+
+        time.update(0.25);
+        assert!(!time.is_frame_running());
+        assert_eq!(time.delta_time, 0.0);
+        assert_eq!(time.unscaled_delta_time, 0.125);
+
+        time.step_frame();
+        time.update(0.375);
+        assert!(time.is_frame_running());
+        assert_eq!(time.delta_time, 0.125);
+        assert_eq!(time.frame_count, 2);
+
+        time.update(0.5);
+        assert!(!time.is_frame_running());
+        assert_eq!(time.frame_count, 2);
+
+        // stepping only works while paused
+        time.resume();
+        time.step_frame();
+        time.pause();
+        time.update(0.625);
+        assert!(!time.is_frame_running());
+    }
+
+    #[test]
+    fn frame_count_and_elapsed_time_stop_while_paused() {
+        let mut time = Time::new(0.0);
+        time.update(0.125);
+        time.pause();
+
+        for frame in 2..=5 {
+            time.update(frame as f32 * 0.125);
+        }
+        assert_eq!(time.frame_count, 1);
+        assert_eq!(time.elapsed_time, 0.125);
+        assert_eq!(time.unscaled_elapsed_time, 0.625);
+
+        time.resume();
+        time.update(0.75);
+        assert_eq!(time.frame_count, 2);
+        assert_eq!(time.elapsed_time, 0.25);
+    }
+
+    #[test]
+    fn long_frames_are_clamped_to_max_delta_time() {
+        let mut time = Time::new(0.0);
+        time.set_time_scale(0.5);
+
+        time.update(5.0);
+        assert_eq!(time.unscaled_delta_time, 0.25);
+        assert_eq!(time.delta_time, 0.125);
+
+        time.set_max_delta_time(1.0);
+        time.update(10.0);
+        assert_eq!(time.unscaled_delta_time, 1.0);
+
+        // a clock going backwards doesn't run time in reverse
+        time.update(9.0);
+        assert_eq!(time.unscaled_delta_time, 0.0);
+        assert_eq!(time.elapsed_time, 0.625);
+    }
+
+    #[test]
+    fn recorded_frames_replay_the_same_input_and_time() {
+        let mut state = new_state();
+        state.start_recording();
+        run_frame(&mut state, 0.016, vec![InputEvent::KeyPressed(key(Key::A))]);
+        run_frame(&mut state, 0.033, vec![InputEvent::MouseMoved(10.0, 20.0)]);
+        run_frame(&mut state, 0.05, vec![InputEvent::KeyReleased(key(Key::A))]);
+        let recording = state.stop_recording().unwrap();
+
+        let bytes = recording.to_bytes().unwrap();
+        let recording = InputRecording::from_bytes(&bytes).unwrap();
+        assert_eq!(recording.frame_count(), 3);
+        let first_delta_time = recording.get_frames()[0].unscaled_delta_time;
+
+        let mut replayed = new_state();
+        replayed.start_replay(recording);
+
+        // live input and wall-clock time are ignored while the recording plays
+        run_frame(&mut replayed, 10.0, vec![InputEvent::KeyPressed(key(Key::B))]);
+        assert!(replayed.keyboard.was_key_pressed(Key::A));
+        assert!(!replayed.keyboard.is_key_pressed(Key::B));
+        assert_eq!(replayed.time.unscaled_delta_time, first_delta_time);
+
+        run_frame(&mut replayed, 20.0, Vec::new());
+        assert!(replayed.keyboard.is_key_pressed(Key::A));
+        assert_eq!(replayed.mouse.position, (10.0, 20.0));
+
+        run_frame(&mut replayed, 30.0, Vec::new());
+        assert!(replayed.keyboard.was_key_released(Key::A));
+        assert_eq!(replayed.time.frame_count, state.time.frame_count);
+        assert_eq!(replayed.time.unscaled_elapsed_time, state.time.unscaled_elapsed_time);
+        assert!(replayed.is_replaying());
+    }
+
+    #[test]
+    fn recording_a_replay_keeps_the_replayed_timeline() {
+        let mut state = new_state();
+        state.start_recording();
+        run_frame(&mut state, 0.016, vec![InputEvent::KeyPressed(key(Key::A))]);
+        state.time.pause();
+        run_frame(&mut state, 0.033, Vec::new());
+        state.time.resume();
+        run_frame(&mut state, 0.05, vec![InputEvent::KeyReleased(key(Key::A))]);
+        let recording = state.stop_recording().unwrap();
+
+        let mut replayed = new_state();
+        replayed.start_replay(recording.clone());
+        replayed.start_recording();
+        for frame in 1..=3 {
+            run_frame(&mut replayed, frame as f32 * 10.0, Vec::new());
+        }
+        let rerecorded = replayed.stop_recording().unwrap();
+
+        assert_eq!(rerecorded.frame_count(), recording.frame_count());
+        for (original, copy) in recording.get_frames().iter().zip(rerecorded.get_frames()) {
+            assert_eq!(copy.time, original.time);
+            assert_eq!(copy.unscaled_delta_time, original.unscaled_delta_time);
+            assert_eq!(copy.is_frame_running, original.is_frame_running);
+            assert_eq!(copy.events.len(), original.events.len());
+        }
+    }
+
+    #[test]
+    fn live_input_resumes_on_the_frame_the_replay_runs_out() {
+        let mut state = new_state();
+        state.start_recording();
+        run_frame(&mut state, 0.016, Vec::new());
+        let recording = state.stop_recording().unwrap();
+
+        let mut replayed = new_state();
+        replayed.start_replay(recording);
+        run_frame(&mut replayed, 0.016, Vec::new());
+
+        run_frame(&mut replayed, 0.033, vec![InputEvent::KeyPressed(key(Key::C))]);
+        assert!(!replayed.is_replaying());
+        assert!(replayed.keyboard.was_key_pressed(Key::C));
+    }
+}
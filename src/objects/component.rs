@@ -1,66 +1,238 @@
-use std::{cell::RefCell, rc::{Rc, Weak}};
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
 
 use crate::objects::app_state::AppState;
+use crate::renderer::gl_render::GLRender;
 
 use super::game_object::GameObject;
 
-pub trait ComponentLogic {
+// `Any` lets components be looked up by their concrete type, see `GameObject::get_component`
+// Lifecycle, once per frame at the start of `AppState::update`: a component that became active
+// gets `on_enable` followed by `start` (the first time only), one that became inactive gets `on_disable`.
+// Changes to the enabled state made during a frame are picked up at the next such point,
+// though a disabled component stops updating and drawing right away.
+pub trait ComponentLogic: Any {
     fn start(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {}
+    fn on_enable(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {}
+    fn on_disable(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {}
     fn update(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {}
-    fn draw(&self, _component: Weak<RefCell<Component>>, _context: &web_sys::WebGl2RenderingContext) {}
+    // Runs at a fixed rate of `state.time.fixed_delta_time`, independent of the frame rate
+    fn fixed_update(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {}
+    // Runs once every component got its `update`, e.g. for a camera following a player
+    fn late_update(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {}
+    // Collects what to draw through `GLRender::submit`, the renderer sorts and draws it afterwards
+    fn draw(&self, _component: Weak<RefCell<Component>>, _renderer: &GLRender) {}
+    fn on_canvas_resize(&mut self, _component: Weak<RefCell<Component>>, _width: u32, _height: u32) {}
+    // Called once, at the end of the frame the component or its object was destroyed in,
+    // after `on_disable` if the component was active.
+    // The logic is dropped right after, together with any GPU resources only it owns.
+    fn on_destroy(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {}
+
+    // Components with a lower order run their fixed_update/update/late_update first, across the whole tree.
+    // Equal orders keep the tree order. Meant to be the same for every instance of a type,
+    // it's read once when the component is added.
+    fn execution_order(&self) -> i32 {
+        0
+    }
+}
+
+// A typed reference to the logic of a component.
+// Borrowing the logic of a component from inside its own hooks panics, it's already borrowed there.
+pub struct ComponentHandle<T: ComponentLogic> {
+    component: Weak<RefCell<Component>>,
+    logic: Rc<RefCell<dyn ComponentLogic>>,
+    logic_type: PhantomData<T>,
+}
+
+impl<T: ComponentLogic> ComponentHandle<T> {
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref::map(self.logic.borrow(), |logic| {
+            let logic: &dyn Any = logic;
+            logic.downcast_ref::<T>().expect("Component handle with a mismatched type")
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        RefMut::map(self.logic.borrow_mut(), |logic| {
+            let logic: &mut dyn Any = logic;
+            logic.downcast_mut::<T>().expect("Component handle with a mismatched type")
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn get_component(&self) -> Weak<RefCell<Component>> {
+        self.component.clone()
+    }
+}
+
+impl<T: ComponentLogic> Clone for ComponentHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            component: self.component.clone(),
+            logic: self.logic.clone(),
+            logic_type: PhantomData,
+        }
+    }
 }
 
 pub struct Component {
     is_started: bool,
     is_enabled: bool,
+    // Whether the logic was last told it's enabled, see `update_active_state`
+    is_active: bool,
+    is_destroyed: bool,
     self_ptr: Weak<RefCell<Component>>,
     object: Weak<RefCell<GameObject>>,
-    logic: Box<dyn ComponentLogic>
+    // Known up front, so looking up components by type never borrows their logic
+    logic_type: TypeId,
+    execution_order: i32,
+    // Kept outside of the component's RefCell, so the logic can borrow its own component
+    // and object while running. Taken when the component is torn down.
+    logic: Option<Rc<RefCell<dyn ComponentLogic>>>
 }
 
 #[allow(dead_code)]
 impl Component {
-    pub(in crate::objects) fn start(&mut self, state: &AppState) {
-        if !self.is_enabled {
+    // Sends `on_enable`/`on_disable` when the active state changed since the last call,
+    // and `start` when the component is active for the first time
+    pub(in crate::objects) fn update_active_state(component: &Rc<RefCell<Self>>, is_object_active: bool, state: &AppState) {
+        let (logic, self_ptr, was_active, is_active, is_started) = {
+            let mut component = component.borrow_mut();
+            let is_active = is_object_active && component.is_enabled && !component.is_destroyed;
+            let was_active = component.is_active;
+            let is_started = component.is_started;
+            if is_active == was_active && (is_started || !is_active) {
+                return;
+            }
+
+            component.is_active = is_active;
+            component.is_started |= is_active;
+            (component.logic.clone(), component.self_ptr.clone(), was_active, is_active, is_started)
+        };
+
+        let Some(logic) = logic else {
             return;
+        };
+        if was_active && !is_active {
+            logic.borrow_mut().on_disable(self_ptr.clone(), state);
         }
+        if !was_active && is_active {
+            logic.borrow_mut().on_enable(self_ptr.clone(), state);
+        }
+        if is_active && !is_started {
+            logic.borrow_mut().start(self_ptr, state);
+        }
+    }
+
+    // Active components that weren't disabled or destroyed since
+    pub(in crate::objects) fn is_running(&self) -> bool {
+        self.is_active && self.is_enabled && !self.is_destroyed
+    }
+
+    pub(in crate::objects) fn update(component: &Rc<RefCell<Self>>, state: &AppState) {
+        Component::run(component, |logic, self_ptr| logic.update(self_ptr, state));
+    }
+
+    pub(in crate::objects) fn fixed_update(component: &Rc<RefCell<Self>>, state: &AppState) {
+        Component::run(component, |logic, self_ptr| logic.fixed_update(self_ptr, state));
+    }
+
+    pub(in crate::objects) fn late_update(component: &Rc<RefCell<Self>>, state: &AppState) {
+        Component::run(component, |logic, self_ptr| logic.late_update(self_ptr, state));
+    }
+
+    // Runs the hook with the component itself not borrowed, if it's running.
+    // Components are collected before a pass starts, so the hierarchy is checked again here
+    // in case an earlier component disabled or destroyed an ancestor.
+    fn run(component: &Rc<RefCell<Self>>, hook: impl FnOnce(&mut dyn ComponentLogic, Weak<RefCell<Component>>)) {
+        let (logic, self_ptr) = {
+            let component = component.borrow();
+            if !component.is_running() || !component.is_active_in_hierarchy() {
+                return;
+            }
+            (component.logic.clone(), component.self_ptr.clone())
+        };
 
-        if !self.is_started {
-            self.logic.start(self.self_ptr.clone(), state);
-            self.is_started = true;
+        if let Some(logic) = logic {
+            hook(&mut *logic.borrow_mut(), self_ptr);
         }
     }
 
-    pub(in crate::objects) fn update(&mut self, state: &AppState) {
-        if !self.is_enabled {
+    pub(in crate::objects) fn draw(&self, renderer: &GLRender) {
+        if !self.is_running() {
             return;
         }
 
-        self.logic.update(self.self_ptr.clone(), state);
+        if let Some(logic) = &self.logic {
+            logic.borrow().draw(self.self_ptr.clone(), renderer);
+        }
     }
 
-    pub(in crate::objects) fn draw(&self, context: &web_sys::WebGl2RenderingContext) {
-        if !self.is_enabled {
-            return;
+    // Resize notifications reach disabled components too, so they are up to date once enabled
+    pub(in crate::objects) fn on_canvas_resize(&self, width: u32, height: u32) {
+        if let Some(logic) = &self.logic {
+            logic.borrow_mut().on_canvas_resize(self.self_ptr.clone(), width, height);
+        }
+    }
+
+    // Calls `on_destroy` and drops the logic, the component itself may outlive it
+    // if something still holds a reference, but it won't run anymore
+    pub(in crate::objects) fn tear_down(component: &Rc<RefCell<Self>>, state: &AppState) {
+        let (logic, self_ptr, was_active) = {
+            let mut component = component.borrow_mut();
+            let was_active = component.is_active;
+            component.is_destroyed = true;
+            component.is_active = false;
+            (component.logic.take(), component.self_ptr.clone(), was_active)
+        };
+
+        if let Some(logic) = logic {
+            let mut logic = logic.borrow_mut();
+            if was_active {
+                logic.on_disable(self_ptr.clone(), state);
+            }
+            logic.on_destroy(self_ptr, state);
         }
+    }
 
-        self.logic.draw(self.self_ptr.clone(), context);
+    pub(in crate::objects) fn get_execution_order(&self) -> i32 {
+        self.execution_order
     }
 
-    pub(in crate::objects) fn new_rc(lgc: Box<dyn ComponentLogic>, object: Weak<RefCell<GameObject>>) -> Rc<RefCell<Self>> {
+    pub(in crate::objects) fn new_rc<T: ComponentLogic>(lgc: T, object: Weak<RefCell<GameObject>>) -> Rc<RefCell<Self>> {
         let new_object = Rc::new(RefCell::new(
             Self {
                 is_started: false,
                 is_enabled: true,
+                is_active: false,
+                is_destroyed: false,
                 self_ptr: Weak::new(),
                 object,
-                logic: lgc,
+                logic_type: TypeId::of::<T>(),
+                execution_order: lgc.execution_order(),
+                logic: Some(Rc::new(RefCell::new(lgc))),
             }
         ));
         new_object.borrow_mut().self_ptr = Rc::downgrade(&new_object);
         new_object
     }
 
+    // `None` if the logic is of a different type, or the component was already torn down
+    pub fn get_logic<T: ComponentLogic>(&self) -> Option<ComponentHandle<T>> {
+        if self.logic_type != TypeId::of::<T>() {
+            return None;
+        }
+
+        Some(ComponentHandle {
+            component: self.self_ptr.clone(),
+            logic: self.logic.clone()?,
+            logic_type: PhantomData,
+        })
+    }
+
     pub fn get_object(&self) -> Weak<RefCell<GameObject>> {
         self.object.clone()
     }
@@ -72,4 +244,225 @@ impl Component {
     pub fn set_enabled(&mut self, is_enabled: bool) {
         self.is_enabled = is_enabled;
     }
-}
\ No newline at end of file
+
+    // Enabled, not destroyed and on an object that's active in the hierarchy
+    pub fn is_active_in_hierarchy(&self) -> bool {
+        self.is_enabled
+            && !self.is_destroyed
+            && self.object.upgrade().is_some_and(|object| object.borrow().is_active_in_hierarchy())
+    }
+
+    // Marks the component for removal at the end of the frame, it stops updating right away
+    pub fn destroy(&mut self) {
+        self.is_destroyed = true;
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.is_destroyed
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::recording_backend::RecordingBackend;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // Writes every hook it gets into a log shared by all components of a test
+    struct Logger {
+        name: &'static str,
+        log: Log,
+        order: i32,
+    }
+
+    impl Logger {
+        fn new(name: &'static str, log: &Log) -> Self {
+            Logger::with_order(name, log, 0)
+        }
+
+        fn with_order(name: &'static str, log: &Log, order: i32) -> Self {
+            Self { name, log: log.clone(), order }
+        }
+
+        fn push(&self, hook: &str) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, hook));
+        }
+    }
+
+    impl ComponentLogic for Logger {
+        fn start(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {
+            self.push("start");
+        }
+
+        fn on_enable(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {
+            self.push("on_enable");
+        }
+
+        fn on_disable(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {
+            self.push("on_disable");
+        }
+
+        fn update(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {
+            self.push("update");
+        }
+
+        fn draw(&self, _component: Weak<RefCell<Component>>, _renderer: &GLRender) {
+            self.push("draw");
+        }
+
+        fn on_destroy(&mut self, _component: Weak<RefCell<Component>>, _state: &AppState) {
+            self.push("on_destroy");
+        }
+
+        fn execution_order(&self) -> i32 {
+            self.order
+        }
+    }
+
+    impl Drop for Logger {
+        fn drop(&mut self) {
+            self.push("dropped");
+        }
+    }
+
+    // Runs a closure in `update`, before every other component
+    struct Hook<F: Fn(&AppState) + 'static> {
+        on_update: F,
+    }
+
+    impl<F: Fn(&AppState) + 'static> ComponentLogic for Hook<F> {
+        fn update(&mut self, _component: Weak<RefCell<Component>>, state: &AppState) {
+            (self.on_update)(state);
+        }
+
+        fn execution_order(&self) -> i32 {
+            -1
+        }
+    }
+
+    fn new_state() -> AppState {
+        AppState::new(Rc::new(RecordingBackend::new(640, 480)), 0.0)
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        std::mem::take(&mut *log.borrow_mut())
+    }
+
+    fn get_component<T: ComponentLogic>(object: &Rc<RefCell<GameObject>>) -> Rc<RefCell<Component>> {
+        object.borrow().get_component::<T>().unwrap().get_component().upgrade().unwrap()
+    }
+
+    #[test]
+    fn enable_comes_before_start_and_start_runs_once() {
+        let log = Log::default();
+        let mut state = new_state();
+        let object = state.add_object_empy();
+        object.borrow_mut().add_component(Logger::new("a", &log));
+
+        state.update(0.016);
+        state.draw();
+        assert_eq!(take(&log), ["a on_enable", "a start", "a update", "a draw"]);
+
+        let component = get_component::<Logger>(&object);
+        component.borrow_mut().set_enabled(false);
+        state.update(0.033);
+        state.draw();
+        assert_eq!(take(&log), ["a on_disable"]);
+
+        component.borrow_mut().set_enabled(true);
+        state.update(0.05);
+        assert_eq!(take(&log), ["a on_enable", "a update"]);
+
+        object.borrow_mut().set_enabled(false);
+        state.update(0.066);
+        state.draw();
+        assert_eq!(take(&log), ["a on_disable"]);
+    }
+
+    #[test]
+    fn disabled_component_stops_updating_and_drawing_right_away() {
+        let log = Log::default();
+        let mut state = new_state();
+        let object = state.add_object_empy();
+        object.borrow_mut().add_component(Logger::new("a", &log));
+        state.update(0.016);
+        take(&log);
+
+        let component = get_component::<Logger>(&object);
+        component.borrow_mut().set_enabled(false);
+        state.draw();
+        assert!(take(&log).is_empty());
+        component.borrow_mut().set_enabled(true);
+
+        // disabled by another component in the middle of the update pass
+        let target = Rc::downgrade(&component);
+        state.add_object_empy().borrow_mut().add_component(Hook {
+            on_update: move |_state: &AppState| target.upgrade().unwrap().borrow_mut().set_enabled(false),
+        });
+        state.update(0.033);
+        state.draw();
+        assert!(take(&log).is_empty());
+
+        state.update(0.05);
+        assert_eq!(take(&log), ["a on_disable"]);
+    }
+
+    #[test]
+    fn lower_execution_order_runs_first_across_the_tree() {
+        let log = Log::default();
+        let mut state = new_state();
+        let first = state.add_object(GameObject::new_named("First"));
+        first.borrow_mut().add_component(Logger::new("first", &log));
+        let child = first.borrow_mut().add_child(GameObject::new_named("Child"));
+        child.borrow_mut().add_component(Logger::with_order("child late", &log, 5));
+        child.borrow_mut().add_component(Logger::new("child", &log));
+        let second = state.add_object(GameObject::new_named("Second"));
+        second.borrow_mut().add_component(Logger::with_order("second early", &log, -1));
+        second.borrow_mut().add_component(Logger::new("second", &log));
+
+        state.update(0.016);
+        let updates: Vec<String> = take(&log).into_iter().filter(|entry| entry.ends_with(" update")).collect();
+        assert_eq!(updates, ["second early update", "first update", "child update", "second update", "child late update"]);
+    }
+
+    #[test]
+    fn destroyed_subtree_is_torn_down_at_the_end_of_the_frame() {
+        let log = Log::default();
+        let mut state = new_state();
+        let parent = state.add_object(GameObject::new_named("Parent"));
+        parent.borrow_mut().add_component(Logger::new("parent", &log));
+        let child = parent.borrow_mut().add_child(GameObject::new_named("Child"));
+        child.borrow_mut().add_component(Logger::new("child", &log));
+        state.update(0.016);
+        take(&log);
+
+        let target = Rc::downgrade(&parent);
+        state.add_object_empy().borrow_mut().add_component(Hook {
+            on_update: move |_state: &AppState| target.upgrade().unwrap().borrow_mut().destroy(),
+        });
+        let watcher_log = log.clone();
+        state.add_object_empy().borrow_mut().add_component(Hook {
+            on_update: move |state: &AppState| {
+                let is_found = state.find_by_path("Parent/Child").is_some();
+                watcher_log.borrow_mut().push(format!("child found {}", is_found));
+            },
+        });
+        state.update(0.033);
+        assert_eq!(take(&log), [
+            "child found true",
+            "child on_disable",
+            "child on_destroy",
+            "child dropped",
+            "parent on_disable",
+            "parent on_destroy",
+            "parent dropped",
+        ]);
+        assert!(state.find_by_path("Parent").is_none());
+        assert!(child.borrow().is_destroyed());
+        assert!(parent.borrow().get_component::<Logger>().is_none());
+
+        state.update(0.05);
+        state.draw();
+        assert_eq!(take(&log), ["child found false"]);
+    }
+}
@@ -1,15 +1,27 @@
+use std::collections::{BTreeSet, VecDeque};
 use std::rc::Weak;
 use std::{cell::RefCell, rc::Rc};
 
+use glm::Mat4;
+
+use crate::console;
+use crate::drawables::camera::Camera;
 use crate::objects::app_state::AppState;
 use crate::objects::component::Component;
+use crate::renderer::gl_render::GLRender;
+use crate::utils::matrix_utils::MatrixUtils;
 
 
-use super::component::ComponentLogic;
+use super::component::{ComponentHandle, ComponentLogic};
 use super::transform::{Transform, TransformData};
 
 pub struct GameObject {
+    name: String,
+    tags: BTreeSet<String>,
     is_enabled: bool,
+    is_destroyed: bool,
+    // Set on the root of the scene, see `set_as_root_node`
+    is_root: bool,
     transform_data: TransformData,
     components: Vec<Rc<RefCell<Component>>>,
     parent: Option<Weak<RefCell<GameObject>>>,
@@ -19,77 +31,387 @@ pub struct GameObject {
 
 #[allow(dead_code)]
 impl GameObject {
-    pub fn start(&mut self, state: &AppState) {
-        if !self.is_enabled {
-            return;
+    // Start and update don't keep the object borrowed while components run,
+    // so components are free to modify it, add children or destroy things.
+    // Children and components added meanwhile are picked up the next frame.
+    // Visits the whole tree, inactive parts included, so components below
+    // a freshly disabled object get their `on_disable` too
+    pub fn update_active_state(object: &Rc<RefCell<Self>>, is_parent_active: bool, state: &AppState) {
+        let (is_active, components, children) = {
+            let object = object.borrow();
+            let is_active = is_parent_active && object.is_enabled && !object.is_destroyed;
+            (is_active, object.components.clone(), object.children.clone())
+        };
+
+        for component in components.iter() {
+            Component::update_active_state(component, is_active, state);
         }
 
-        for component in self.components.iter_mut() {
-            component.borrow_mut().start(state);
+        for child in children.iter() {
+            GameObject::update_active_state(child, is_active, state);
         }
+    }
 
-        for child in self.children.iter_mut() {
-            child.borrow_mut().start(state);
+    // The update passes run over every active component in the tree at once,
+    // ordered by `ComponentLogic::execution_order`, not object by object
+    pub fn update(object: &Rc<RefCell<Self>>, state: &AppState) {
+        for component in GameObject::get_execution_list(object).iter() {
+            Component::update(component, state);
         }
     }
 
-    pub fn update(&mut self, state: &AppState) {
-        if !self.is_enabled {
-            return;
+    // Runs zero or more times per frame, before `update`, see `Time::take_fixed_steps`
+    pub fn fixed_update(object: &Rc<RefCell<Self>>, state: &AppState) {
+        for component in GameObject::get_execution_list(object).iter() {
+            Component::fixed_update(component, state);
         }
+    }
 
-        for component in self.components.iter_mut() {
-            component.borrow_mut().update(state);
+    pub fn late_update(object: &Rc<RefCell<Self>>, state: &AppState) {
+        for component in GameObject::get_execution_list(object).iter() {
+            Component::late_update(component, state);
         }
+    }
 
-        for child in self.children.iter_mut() {
-            child.borrow_mut().update(state);
+    fn get_execution_list(object: &Rc<RefCell<Self>>) -> Vec<Rc<RefCell<Component>>> {
+        let mut components = Vec::new();
+        GameObject::for_each_active_component(object, &mut |component| components.push(component.clone()));
+        // stable, so equal orders stay in tree order
+        components.sort_by_key(|component| component.borrow().get_execution_order());
+        components
+    }
+
+    fn for_each_active_component(object: &Rc<RefCell<Self>>, f: &mut impl FnMut(&Rc<RefCell<Component>>)) {
+        let (components, children) = {
+            let object = object.borrow();
+            if !object.is_enabled || object.is_destroyed {
+                return;
+            }
+            (object.components.clone(), object.children.clone())
+        };
+
+        for component in components.iter() {
+            f(component);
+        }
+
+        for child in children.iter() {
+            GameObject::for_each_active_component(child, f);
         }
     }
 
-    pub fn draw(&self, context: &web_sys::WebGl2RenderingContext) {
+    pub fn draw(&self, renderer: &GLRender) {
         if !self.is_enabled {
             return;
         }
 
         for component in self.components.iter() {
-            component.borrow().draw(context);
+            component.borrow().draw(renderer);
         }
 
         for child in self.children.iter() {
-            child.borrow().draw(context);
+            child.borrow().draw(renderer);
         }
     }
 
+    pub fn on_canvas_resize(&self, width: u32, height: u32) {
+        for component in self.components.iter() {
+            component.borrow().on_canvas_resize(width, height);
+        }
+
+        for child in self.children.iter() {
+            child.borrow().on_canvas_resize(width, height);
+        }
+    }
+
+    // Returns the view and projection matrices of the first enabled camera in the tree
+    pub fn find_camera_matrices(&self) -> Option<(Mat4, Mat4)> {
+        if !self.is_enabled {
+            return None;
+        }
+
+        for component in self.components.iter() {
+            let component = component.borrow();
+            if !component.is_running() {
+                continue;
+            }
+            if let Some(camera) = component.get_logic::<Camera>() {
+                let view = Camera::view_matrix(&self.transform_data.world_space_matrix);
+                return Some((view, camera.borrow().projection_matrix()));
+            }
+        }
+
+        self.children.iter().find_map(|child| child.borrow().find_camera_matrices())
+    }
+
     pub(in crate::objects) fn set_as_root_node(&mut self, reference: Rc<RefCell<GameObject>>) {
         self.self_reference = Some(Rc::downgrade(&reference));
+        self.is_root = true;
     }
 
     pub fn new() -> Rc<RefCell<Self>> {
-        let new_object = Rc::new(RefCell::new(Self {
+        GameObject::new_named("GameObject")
+    }
+
+    pub fn new_named(name: &str) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            name: name.to_string(),
+            tags: BTreeSet::new(),
             components: vec![],
             transform_data: TransformData::new(),
             is_enabled: true,
+            is_destroyed: false,
+            is_root: false,
             self_reference: None,
             parent: None,
             children: vec![]
-        }));
-        new_object
+        }))
     }
 
     pub fn add_component<T: ComponentLogic + 'static>(&mut self, logic: T) {
         let new_ref = self.self_reference.as_ref().expect("Self reference not found. Object was not Instantiated!").clone();
-        self.components.push(Component::new_rc(Box::new(logic), new_ref));
+        self.components.push(Component::new_rc(logic, new_ref));
     }
 
     pub fn add_child(&mut self, child: Rc<RefCell<GameObject>>) -> Rc<RefCell<GameObject>> {
-        let child = child.clone();
+        if GameObject::is_self_or_ancestor(self, &child) {
+            console::error!("Cannot add an object as a child of itself or of one of its descendants");
+            return child;
+        }
+
+        let old_parent = child.borrow().parent.clone();
+        if let Some(old_parent) = old_parent {
+            let is_same_parent = self
+                .self_reference
+                .as_ref()
+                .is_some_and(|self_reference| self_reference.ptr_eq(&old_parent));
+            if is_same_parent {
+                return child;
+            }
+            // the previous parent would otherwise keep updating and drawing it too
+            if let Some(old_parent) = old_parent.upgrade() {
+                old_parent.borrow_mut().remove_child(&child);
+            }
+        }
+
         child.borrow_mut().parent = self.self_reference.clone();
         child.borrow_mut().self_reference = Some(Rc::downgrade(&child));
         self.children.push(child.clone());
         child
     }
 
+    // Component lookups by logic type. The `_in_children` and `_in_parent` variants
+    // check the object itself first, then its descendants (depth-first) or ancestors.
+    pub fn get_component<T: ComponentLogic>(&self) -> Option<ComponentHandle<T>> {
+        self.components.iter().find_map(|component| component.borrow().get_logic::<T>())
+    }
+
+    pub fn get_components<T: ComponentLogic>(&self) -> Vec<ComponentHandle<T>> {
+        self.components.iter().filter_map(|component| component.borrow().get_logic::<T>()).collect()
+    }
+
+    pub fn get_component_in_children<T: ComponentLogic>(&self) -> Option<ComponentHandle<T>> {
+        self.get_component::<T>()
+            .or_else(|| self.children.iter().find_map(|child| child.borrow().get_component_in_children::<T>()))
+    }
+
+    pub fn get_components_in_children<T: ComponentLogic>(&self) -> Vec<ComponentHandle<T>> {
+        let mut components = self.get_components::<T>();
+        for child in self.children.iter() {
+            components.extend(child.borrow().get_components_in_children::<T>());
+        }
+        components
+    }
+
+    pub fn get_component_in_parent<T: ComponentLogic>(&self) -> Option<ComponentHandle<T>> {
+        self.get_component::<T>().or_else(|| {
+            let parent = self.parent.as_ref()?.upgrade()?;
+            let component = parent.borrow().get_component_in_parent::<T>();
+            component
+        })
+    }
+
+    // Detaches the child without destroying it, it keeps running only if added somewhere else
+    pub fn remove_child(&mut self, child: &Rc<RefCell<GameObject>>) -> Option<Rc<RefCell<GameObject>>> {
+        let index = self.children.iter().position(|other| Rc::ptr_eq(other, child))?;
+        let child = self.children.remove(index);
+        child.borrow_mut().parent = None;
+        Some(child)
+    }
+
+    // Marks the object and everything below it for removal at the end of the frame.
+    // It stops updating right away. The root object can't be destroyed.
+    pub fn destroy(&mut self) {
+        if self.is_root {
+            return;
+        }
+        self.is_destroyed = true;
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.is_destroyed
+    }
+
+    // Tears down destroyed components and objects in the tree, runs at the end of `AppState::update`
+    pub(in crate::objects) fn remove_destroyed(object: &Rc<RefCell<Self>>, state: &AppState) {
+        let (components, children) = {
+            let object = object.borrow();
+            (object.components.clone(), object.children.clone())
+        };
+
+        let destroyed_components: Vec<_> = components.iter().filter(|component| component.borrow().is_destroyed()).collect();
+        if !destroyed_components.is_empty() {
+            for component in destroyed_components.iter() {
+                Component::tear_down(component, state);
+            }
+            object.borrow_mut().components.retain(|component| !component.borrow().is_destroyed());
+        }
+
+        for child in children.iter() {
+            if child.borrow().is_destroyed {
+                GameObject::tear_down(child, state);
+                object.borrow_mut().children.retain(|other| !Rc::ptr_eq(other, child));
+            } else {
+                GameObject::remove_destroyed(child, state);
+            }
+        }
+    }
+
+    // Children go first, so their `on_destroy` still sees the parent intact
+    fn tear_down(object: &Rc<RefCell<Self>>, state: &AppState) {
+        let children = object.borrow().children.clone();
+        for child in children.iter() {
+            GameObject::tear_down(child, state);
+        }
+
+        let components = object.borrow().components.clone();
+        for component in components.iter() {
+            Component::tear_down(component, state);
+        }
+
+        let mut object = object.borrow_mut();
+        object.is_destroyed = true;
+        object.components.clear();
+        object.children.clear();
+        object.parent = None;
+    }
+
+    // Moves the object under `new_parent`, or directly under the root of its hierarchy when `None`.
+    // With `keep_world_transform` the object stays where it is in the world and its local
+    // position, rotation and scale are recomputed, otherwise the locals are kept as they are.
+    pub fn set_parent(
+        object: &Rc<RefCell<Self>>,
+        new_parent: Option<&Rc<RefCell<Self>>>,
+        keep_world_transform: bool,
+    ) -> Result<(), String> {
+        let new_parent = match new_parent {
+            Some(new_parent) => new_parent.clone(),
+            None if object.borrow().parent.is_none() => {
+                return Err("Object has no root to attach to".to_string());
+            }
+            None => GameObject::get_root(object),
+        };
+
+        // the new parent can't be the object itself or anything below it
+        if GameObject::is_self_or_ancestor(&new_parent.borrow(), object) {
+            return Err("Cannot parent an object to itself or to one of its descendants".to_string());
+        }
+
+        let world_space_matrix = object.borrow().get_world_space_matrx();
+        new_parent.borrow_mut().add_child(object.clone());
+
+        let mut object = object.borrow_mut();
+        if keep_world_transform {
+            let parent_matrix = new_parent.borrow().get_world_space_matrx();
+            let local_matrix = glm::inverse(&parent_matrix) * world_space_matrix;
+            let decomp = MatrixUtils::decompose_matrix(local_matrix);
+            let data = object.get_data_mut();
+            data.local_position = decomp.translation;
+            data.local_rotation = decomp.rotation;
+            data.local_scale = decomp.scale;
+        }
+        object.update_matrix();
+
+        Ok(())
+    }
+
+    // Whether `object` is `parent` or one of its ancestors, adding it under `parent` would make a cycle.
+    // `parent` may be borrowed already, so it's only compared by address.
+    fn is_self_or_ancestor(parent: &GameObject, object: &Rc<RefCell<Self>>) -> bool {
+        if std::ptr::eq(parent, object.as_ptr()) {
+            return true;
+        }
+        let mut ancestor = parent.parent.as_ref().and_then(|parent| parent.upgrade());
+        while let Some(current) = ancestor {
+            if Rc::ptr_eq(&current, object) {
+                return true;
+            }
+            ancestor = current.borrow().parent.as_ref().and_then(|parent| parent.upgrade());
+        }
+        false
+    }
+
+    // The topmost ancestor, or the object itself when it has no parent
+    pub fn get_root(object: &Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        let mut root = object.clone();
+        loop {
+            let parent = root.borrow().parent.as_ref().and_then(|parent| parent.upgrade());
+            match parent {
+                Some(parent) => root = parent,
+                None => return root,
+            }
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    // Names don't have to be unique, but '/' can't be used in `find_by_path` lookups
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn add_tag(&mut self, tag: &str) {
+        self.tags.insert(tag.to_string());
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.remove(tag);
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    pub fn get_tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(|tag| tag.as_str())
+    }
+
+    // First direct child with the given name
+    pub fn find_child(&self, name: &str) -> Option<Rc<RefCell<GameObject>>> {
+        self.children.iter().find(|child| child.borrow().name == name).cloned()
+    }
+
+    // Follows child names separated by '/', relative to this object
+    pub fn find_by_path(&self, path: &str) -> Option<Rc<RefCell<GameObject>>> {
+        let mut names = path.split('/').filter(|name| !name.is_empty());
+        let mut current = self.find_child(names.next()?)?;
+        for name in names {
+            let next = current.borrow().find_child(name)?;
+            current = next;
+        }
+        Some(current)
+    }
+
+    // Iterators over the object and everything below it, the object itself comes first
+    pub fn iter_depth_first(object: &Rc<RefCell<Self>>) -> DepthFirstIter {
+        DepthFirstIter { stack: vec![object.clone()] }
+    }
+
+    pub fn iter_breadth_first(object: &Rc<RefCell<Self>>) -> BreadthFirstIter {
+        BreadthFirstIter { queue: VecDeque::from([object.clone()]) }
+    }
+
     pub fn has_parent(&self) -> bool {
         self.parent.is_some()
     }
@@ -98,9 +420,27 @@ impl GameObject {
         self.parent.clone()
     }
 
+    // Disabling stops updates of the object and its children right away,
+    // the components are notified at the start of the next frame
     pub fn set_enabled(&mut self, enabled: bool) {
         self.is_enabled = enabled;
     }
+
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    // Enabled, not destroyed and with every ancestor enabled as well
+    pub fn is_active_in_hierarchy(&self) -> bool {
+        if !self.is_enabled || self.is_destroyed {
+            return false;
+        }
+
+        match self.parent.as_ref().and_then(|parent| parent.upgrade()) {
+            Some(parent) => parent.borrow().is_active_in_hierarchy(),
+            None => true,
+        }
+    }
 }
 
 impl Transform for GameObject {
@@ -119,4 +459,120 @@ impl Transform for GameObject {
     fn get_children(&self) -> Vec<Weak<RefCell<Self>>> {
         self.children.iter().map(|child| child.borrow().self_reference.clone().unwrap()).collect()
     }
-}
\ No newline at end of file
+}
+
+// Children are read when their parent is visited, so changes to
+// the hierarchy made while iterating show up only in parts not visited yet.
+pub struct DepthFirstIter {
+    stack: Vec<Rc<RefCell<GameObject>>>,
+}
+
+impl Iterator for DepthFirstIter {
+    type Item = Rc<RefCell<GameObject>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let object = self.stack.pop()?;
+        // reversed, so the first child is popped first
+        self.stack.extend(object.borrow().children.iter().rev().cloned());
+        Some(object)
+    }
+}
+
+pub struct BreadthFirstIter {
+    queue: VecDeque<Rc<RefCell<GameObject>>>,
+}
+
+impl Iterator for BreadthFirstIter {
+    type Item = Rc<RefCell<GameObject>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let object = self.queue.pop_front()?;
+        self.queue.extend(object.borrow().children.iter().cloned());
+        Some(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A root with `Parent/Child` below it
+    fn hierarchy() -> Rc<RefCell<GameObject>> {
+        let root = GameObject::new_named("Root");
+        root.borrow_mut().set_as_root_node(root.clone());
+        let parent = root.borrow_mut().add_child(GameObject::new_named("Parent"));
+        parent.borrow_mut().add_child(GameObject::new_named("Child"));
+        root
+    }
+
+    #[test]
+    fn root_is_never_destroyed() {
+        let root = hierarchy();
+        let parent = root.borrow().find_by_path("Parent").unwrap();
+
+        root.borrow_mut().destroy();
+        parent.borrow_mut().destroy();
+        assert!(!root.borrow().is_destroyed());
+        assert!(parent.borrow().is_destroyed());
+    }
+
+    #[test]
+    fn set_parent_without_a_parent_moves_under_the_root() {
+        let root = hierarchy();
+        let parent = root.borrow().find_by_path("Parent").unwrap();
+        let child = root.borrow().find_by_path("Parent/Child").unwrap();
+
+        GameObject::set_parent(&child, None, false).unwrap();
+        assert!(parent.borrow().find_child("Child").is_none());
+        assert!(Rc::ptr_eq(&root.borrow().find_child("Child").unwrap(), &child));
+    }
+
+    #[test]
+    fn set_parent_without_a_parent_needs_a_root() {
+        let lonely = GameObject::new_named("Lonely");
+
+        let err = GameObject::set_parent(&lonely, None, false).unwrap_err();
+        assert_eq!(err, "Object has no root to attach to");
+    }
+
+    #[test]
+    fn set_parent_can_keep_the_world_transform() {
+        let root = hierarchy();
+        let parent = root.borrow().find_by_path("Parent").unwrap();
+        {
+            let mut parent = parent.borrow_mut();
+            parent.set_local_position(glm::vec3(-4.0, 1.0, 0.5));
+            parent.set_local_rotation(glm::quat_angle_axis(0.7, &glm::vec3(0.0, 1.0, 0.0)));
+            parent.set_local_scale(glm::vec3(2.0, 2.0, 2.0));
+        }
+        let object = root.borrow_mut().add_child(GameObject::new_named("Object"));
+        {
+            let mut object = object.borrow_mut();
+            object.set_local_position(glm::vec3(1.0, 2.0, 3.0));
+            object.set_local_rotation(glm::quat_angle_axis(-0.4, &glm::vec3(1.0, 0.0, 0.0)));
+        }
+        let below = object.borrow_mut().add_child(GameObject::new_named("Below"));
+        below.borrow_mut().set_local_position(glm::vec3(0.0, 1.0, 0.0));
+        let world_space_matrix = object.borrow().get_world_space_matrx();
+        let below_matrix = below.borrow().get_world_space_matrx();
+
+        GameObject::set_parent(&object, Some(&parent), true).unwrap();
+
+        assert!(glm::abs(&(below.borrow().get_world_space_matrx() - below_matrix)).max() < 1e-4);
+        let object = object.borrow();
+        assert!(glm::abs(&(object.get_world_space_matrx() - world_space_matrix)).max() < 1e-4);
+        assert!((glm::quat_magnitude(&object.get_local_rotation()) - 1.0).abs() < 1e-4);
+        assert!(glm::abs(&(object.get_data().local_scale - glm::vec3(0.5, 0.5, 0.5))).max() < 1e-4);
+    }
+
+    #[test]
+    fn set_parent_rejects_cycles() {
+        let root = hierarchy();
+        let parent = root.borrow().find_by_path("Parent").unwrap();
+        let child = root.borrow().find_by_path("Parent/Child").unwrap();
+
+        assert!(GameObject::set_parent(&parent, Some(&child), false).is_err());
+        assert!(GameObject::set_parent(&parent, Some(&parent), false).is_err());
+        assert!(Rc::ptr_eq(&parent.borrow().find_child("Child").unwrap(), &child));
+    }
+}
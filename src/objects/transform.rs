@@ -43,7 +43,7 @@ pub trait Transform {
     fn calculate_local_model_matrix(&self) -> Mat4 {
         let mut translation_matrix = Mat4::identity();
         translation_matrix = glm::translate(&translation_matrix, &self.get_data().local_position);
-        translation_matrix = translation_matrix * glm::quat_to_mat4(&self.get_data().local_rotation);
+        translation_matrix *= glm::quat_to_mat4(&self.get_data().local_rotation);
         translation_matrix = glm::scale(&translation_matrix, &self.get_data().local_scale);
 
         translation_matrix
@@ -51,12 +51,16 @@ pub trait Transform {
 
     fn update_matrix(&mut self) {
         let mut parent_matrix = Mat4::identity();
-        let parent = self.get_parent();
-        if parent.is_some() {
-            let parent = parent.unwrap().upgrade().unwrap();
+        if let Some(parent) = self.get_parent() {
+            let parent = parent.upgrade().unwrap();
             parent_matrix = parent.borrow().get_data().world_space_matrix;
         }
 
+        self.update_matrix_with_parent(parent_matrix);
+    }
+
+    // Children get the matrix passed down, their parent is still borrowed while they update
+    fn update_matrix_with_parent(&mut self, parent_matrix: Mat4) {
         self.get_data_mut().world_space_matrix = parent_matrix * self.calculate_local_model_matrix();
         let dec = MatrixUtils::decompose_matrix(self.get_data().world_space_matrix);
         self.get_data_mut().global_position = dec.translation;
@@ -64,9 +68,10 @@ pub trait Transform {
         self.get_data_mut().global_scale = dec.scale;
 
         // self.get_object().upgrade().unwrap().borrow_mut().update_children_transform_matrix();
+        let world_space_matrix = self.get_data().world_space_matrix;
         let children = self.get_children();
         for child in children {
-            child.upgrade().unwrap().borrow_mut().update_matrix();
+            child.upgrade().unwrap().borrow_mut().update_matrix_with_parent(world_space_matrix);
         }
     }
 
@@ -84,9 +89,8 @@ pub trait Transform {
     }
 
     fn set_global_position(&mut self, position: Vec3) {
-        let parent = self.get_parent();
-        if parent.is_some() {
-            let parent_matrix = parent.unwrap().upgrade().unwrap().borrow().get_world_space_matrx();
+        if let Some(parent) = self.get_parent() {
+            let parent_matrix = parent.upgrade().unwrap().borrow().get_world_space_matrx();
             let parent_inverse = glm::inverse(&parent_matrix);
             let local_position = glm::Vec4::new(position.x, position.y, position.z, 1.0);
 
@@ -101,9 +105,8 @@ pub trait Transform {
     }
 
     fn set_global_rotation(&mut self, rotation: Quat) {
-        let parent = self.get_parent();
-        if parent.is_some() {
-            let parent_matrix = parent.unwrap().upgrade().unwrap().borrow().get_world_space_matrx();
+        if let Some(parent) = self.get_parent() {
+            let parent_matrix = parent.upgrade().unwrap().borrow().get_world_space_matrx();
             let parent_inverse = glm::inverse(&parent_matrix);
             let local_rotation = glm::quat_to_mat4(&self.get_data().local_rotation);
             let relative_rotation = parent_inverse * local_rotation;
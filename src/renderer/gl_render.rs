@@ -1,25 +1,260 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
-use web_sys::WebGl2RenderingContext;
+use glm::Mat4;
 
 use crate::objects::{app_state::AppState, game_object::GameObject};
+use crate::renderer::material::{BlendMode, Material, UniformValue};
+use crate::renderer::mesh::Mesh;
+use crate::renderer::render_backend::RenderBackend;
+use crate::renderer::render_queue::{DrawItem, RenderQueue};
+use crate::renderer::state_cache::StateCache;
 
 pub struct GLRender {
-    context: WebGl2RenderingContext,
+    backend: Rc<dyn RenderBackend>,
+    view_matrix: Cell<Mat4>,
+    projection_matrix: Cell<Mat4>,
+    interpolation_alpha: Cell<f32>,
+    queue: RefCell<RenderQueue>,
+    state_cache: RefCell<StateCache>,
 }
 
 impl GLRender {
-    pub fn new(context: WebGl2RenderingContext) -> Self {
+    pub fn new(backend: Rc<dyn RenderBackend>) -> Self {
+        backend.set_depth_test(true);
+
         Self {
-            context
+            backend,
+            view_matrix: Cell::new(Mat4::identity()),
+            projection_matrix: Cell::new(Mat4::identity()),
+            interpolation_alpha: Cell::new(0.0),
+            queue: RefCell::new(RenderQueue::new()),
+            state_cache: RefCell::new(StateCache::new()),
+        }
+    }
+
+    pub fn get_backend(&self) -> &Rc<dyn RenderBackend> {
+        &self.backend
+    }
+
+    // See `Time::interpolation_alpha`
+    #[allow(dead_code)]
+    pub fn get_interpolation_alpha(&self) -> f32 {
+        self.interpolation_alpha.get()
+    }
+
+    pub fn render(&self, state: &AppState, root_object: &RefCell<GameObject>) {
+        self.interpolation_alpha.set(state.time.interpolation_alpha);
+
+        let backend = self.backend.as_ref();
+        let (width, height) = backend.get_drawing_buffer_size();
+        backend.set_viewport(0, 0, width, height);
+        backend.clear(true, true);
+
+        // Without a camera, positions are passed through in clip space
+        let (view, projection) = root_object
+            .borrow()
+            .find_camera_matrices()
+            .unwrap_or((Mat4::identity(), Mat4::identity()));
+        self.view_matrix.set(view);
+        self.projection_matrix.set(projection);
+
+        // Components only submit draw items here, nothing is drawn until the queue is sorted
+        root_object.borrow().draw(self);
+
+        let mut queue = self.queue.borrow_mut();
+        queue.sort();
+
+        let mut state_cache = self.state_cache.borrow_mut();
+        // other code may have touched the GL state since the last frame
+        state_cache.reset();
+        for item in queue.get_opaque().iter().chain(queue.get_transparent().iter()) {
+            self.draw_item(&mut state_cache, item);
         }
+        // back to the defaults, a clear with depth writes off would leave the depth buffer as is
+        state_cache.bind_vertex_array(backend, None);
+        state_cache.set_blend_mode(backend, BlendMode::Opaque);
+        state_cache.set_depth_test(backend, true);
+        state_cache.set_depth_write(backend, true);
+
+        queue.clear();
+    }
+
+    // Queues the mesh to be drawn with the material this frame
+    pub fn submit(&self, mesh: Rc<RefCell<Mesh>>, material: Rc<RefCell<Material>>, model_matrix: Mat4, layer: i32) {
+        // the camera looks down -Z in view space
+        let view_position = self.view_matrix.get() * model_matrix * glm::vec4(0.0, 0.0, 0.0, 1.0);
+
+        self.queue.borrow_mut().push(DrawItem {
+            mesh,
+            material,
+            model_matrix,
+            layer,
+            depth: -view_position.z,
+        });
+    }
+
+    fn draw_item(&self, state_cache: &mut StateCache, item: &DrawItem) {
+        let backend = self.backend.as_ref();
+        let mesh = item.mesh.borrow();
+        let material = item.material.borrow();
+        let program = material.get_program();
+
+        state_cache.use_program(backend, program.get_program());
+        state_cache.set_blend_mode(backend, material.get_blend_mode());
+        state_cache.set_depth_test(backend, material.get_depth_test());
+        state_cache.set_depth_write(backend, material.get_depth_write());
+
+        material.apply_uniforms(backend);
+        program.set_uniform(backend, "u_model", &UniformValue::Mat4(item.model_matrix));
+        program.set_uniform(backend, "u_view", &UniformValue::Mat4(self.view_matrix.get()));
+        program.set_uniform(backend, "u_projection", &UniformValue::Mat4(self.projection_matrix.get()));
+
+        state_cache.bind_vertex_array(backend, mesh.get_vertex_array());
+        mesh.draw(backend);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use web_sys::WebGl2RenderingContext;
+
+    use crate::drawables::mesh_renderer::MeshRenderer;
+    use crate::objects::app_state::AppState;
+    use crate::objects::game_object::GameObject;
+    use crate::objects::transform::Transform;
+    use crate::renderer::material::{BlendMode, Material, UniformValue};
+    use crate::renderer::mesh::{Mesh, VertexLayout};
+    use crate::renderer::recording_backend::{RecordingBackend, RenderCommand};
+    use crate::renderer::render_backend::RenderBackend;
+    use crate::renderer::shader_program::ShaderProgram;
+
+    const VERT_SOURCE: &str = "#version 300 es
+layout(location = 0) in vec4 position;
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+void main() {
+    gl_Position = u_projection * u_view * u_model * position;
+}";
+
+    const FRAG_SOURCE: &str = "#version 300 es
+precision highp float;
+uniform vec4 u_color;
+out vec4 outColor;
+void main() {
+    outColor = u_color;
+}";
+
+    fn setup() -> (Rc<RecordingBackend>, AppState, Rc<ShaderProgram>) {
+        let recording = Rc::new(RecordingBackend::new(640, 480));
+        let backend: Rc<dyn RenderBackend> = recording.clone();
+        let program = Rc::new(ShaderProgram::new(&backend, VERT_SOURCE, FRAG_SOURCE).unwrap());
+        let state = AppState::new(backend, 0.0);
+        (recording, state, program)
     }
 
-    pub fn render(&self, _state: &AppState, root_object: &RefCell<GameObject>) {
-        let context = &self.context;
-        context.viewport(0, 0, context.drawing_buffer_width(), context.drawing_buffer_height());
-        context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+    fn triangle() -> Mesh {
+        let vertices = vec![-0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.0, 0.5, 0.0];
+        Mesh::new(vertices, None, VertexLayout::new().with_attribute(0, 3))
+    }
 
-        root_object.borrow().draw(context);
+    fn add_renderer(state: &mut AppState, name: &str, renderer: MeshRenderer) -> Rc<std::cell::RefCell<GameObject>> {
+        let object = state.add_object(GameObject::new_named(name));
+        object.borrow_mut().add_component(renderer);
+        object
     }
-}
\ No newline at end of file
+
+    // Runs the frame, so components are started, and returns only the commands it drew with
+    fn render_frame(recording: &RecordingBackend, state: &mut AppState) -> Vec<RenderCommand> {
+        state.update(0.016);
+        recording.take_commands();
+        state.draw();
+        recording.get_commands()
+    }
+
+    #[test]
+    fn draw_call_uses_program_and_vertex_array() {
+        let (recording, mut state, program) = setup();
+        let object = add_renderer(&mut state, "Triangle", MeshRenderer::new(triangle(), Material::new(program.clone())));
+
+        render_frame(&recording, &mut state);
+
+        let mesh = object.borrow().get_component::<MeshRenderer>().unwrap().borrow().get_mesh();
+        let vertex_array = mesh.borrow().get_vertex_array();
+        assert!(vertex_array.is_some());
+        assert_eq!(recording.get_draw_calls(), vec![RenderCommand::DrawArrays {
+            program: Some(program.get_program()),
+            vertex_array,
+            primitive: WebGl2RenderingContext::TRIANGLES,
+            first: 0,
+            count: 3,
+        }]);
+    }
+
+    #[test]
+    fn matrices_are_set_on_the_drawing_program() {
+        let (recording, mut state, program) = setup();
+        let object = add_renderer(&mut state, "Triangle", MeshRenderer::new(triangle(), Material::new(program.clone())));
+        object.borrow_mut().set_local_position(glm::vec3(1.0, 2.0, 3.0));
+
+        let commands = render_frame(&recording, &mut state);
+
+        let uniform = |name: &str| {
+            commands.iter().find_map(|command| match command {
+                RenderCommand::SetUniform { program, name: uniform_name, value } if uniform_name == name => {
+                    Some((*program, value.clone()))
+                }
+                _ => None,
+            })
+        };
+        let model_matrix = glm::translate(&glm::Mat4::identity(), &glm::vec3(1.0, 2.0, 3.0));
+        let handle = Some(program.get_program());
+        assert_eq!(uniform("u_model"), Some((handle, UniformValue::Mat4(model_matrix))));
+        assert_eq!(uniform("u_view"), Some((handle, UniformValue::Mat4(glm::Mat4::identity()))));
+        assert_eq!(uniform("u_projection"), Some((handle, UniformValue::Mat4(glm::Mat4::identity()))));
+    }
+
+    #[test]
+    fn opaque_items_are_drawn_before_transparent_ones() {
+        let (recording, mut state, program) = setup();
+        let mut transparent_material = Material::new(program.clone());
+        transparent_material.set_blend_mode(BlendMode::Alpha);
+        // added first, so tree order alone would draw it first
+        let transparent = add_renderer(&mut state, "Transparent", MeshRenderer::new(triangle(), transparent_material));
+        let opaque = add_renderer(&mut state, "Opaque", MeshRenderer::new(triangle(), Material::new(program.clone())));
+
+        render_frame(&recording, &mut state);
+
+        let vertex_array = |object: &Rc<std::cell::RefCell<GameObject>>| {
+            let mesh = object.borrow().get_component::<MeshRenderer>().unwrap().borrow().get_mesh();
+            let vertex_array = mesh.borrow().get_vertex_array();
+            vertex_array
+        };
+        let drawn: Vec<_> = recording
+            .get_draw_calls()
+            .into_iter()
+            .map(|command| match command {
+                RenderCommand::DrawArrays { vertex_array, .. } => vertex_array,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(drawn, vec![vertex_array(&opaque), vertex_array(&transparent)]);
+    }
+
+    #[test]
+    fn redundant_state_changes_are_skipped() {
+        let (recording, mut state, program) = setup();
+        add_renderer(&mut state, "First", MeshRenderer::new(triangle(), Material::new(program.clone())));
+        add_renderer(&mut state, "Second", MeshRenderer::new(triangle(), Material::new(program.clone())));
+
+        let commands = render_frame(&recording, &mut state);
+
+        let count = |matches: fn(&RenderCommand) -> bool| commands.iter().filter(|command| matches(command)).count();
+        assert_eq!(count(|command| matches!(command, RenderCommand::DrawArrays { .. })), 2);
+        assert_eq!(count(|command| matches!(command, RenderCommand::UseProgram(_))), 1);
+        assert_eq!(count(|command| matches!(command, RenderCommand::SetBlendMode(_))), 1);
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use glm::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::renderer::render_backend::RenderBackend;
+use crate::renderer::shader_program::ShaderProgram;
+use crate::renderer::texture::Texture2D;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Int(i32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Mat4(Mat4),
+    // Texture unit the sampler reads from
+    Texture(u32),
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque,
+    // Classic transparency, weighted by the fragment alpha
+    Alpha,
+    // Adds to what's already drawn, for glows and particles
+    Additive,
+}
+
+// A shader program together with the uniform values it should be drawn with.
+// Several materials can share one program, each with its own parameters.
+pub struct Material {
+    program: Rc<ShaderProgram>,
+    uniforms: HashMap<String, UniformValue>,
+    // Sampler name to texture, sorted so every draw assigns the same texture units
+    textures: BTreeMap<String, Rc<Texture2D>>,
+    blend_mode: BlendMode,
+    depth_test: bool,
+    depth_write: bool,
+}
+
+#[allow(dead_code)]
+impl Material {
+    pub fn new(program: Rc<ShaderProgram>) -> Self {
+        Self {
+            program,
+            uniforms: HashMap::new(),
+            textures: BTreeMap::new(),
+            blend_mode: BlendMode::Opaque,
+            depth_test: true,
+            depth_write: true,
+        }
+    }
+
+    // Blending materials are drawn in the transparent pass,
+    // it's usually best to turn their depth writes off as well
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.blend_mode != BlendMode::Opaque
+    }
+
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+
+    pub fn get_depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    pub fn set_depth_write(&mut self, depth_write: bool) {
+        self.depth_write = depth_write;
+    }
+
+    pub fn get_depth_write(&self) -> bool {
+        self.depth_write
+    }
+
+    pub fn get_program(&self) -> Rc<ShaderProgram> {
+        self.program.clone()
+    }
+
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        self.uniforms.insert(name.to_string(), value);
+    }
+
+    pub fn get_uniform(&self, name: &str) -> Option<&UniformValue> {
+        self.uniforms.get(name)
+    }
+
+    pub fn remove_uniform(&mut self, name: &str) -> Option<UniformValue> {
+        self.uniforms.remove(name)
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.set_uniform(name, UniformValue::Float(value));
+    }
+
+    pub fn set_vec2(&mut self, name: &str, value: Vec2) {
+        self.set_uniform(name, UniformValue::Vec2(value));
+    }
+
+    pub fn set_vec3(&mut self, name: &str, value: Vec3) {
+        self.set_uniform(name, UniformValue::Vec3(value));
+    }
+
+    pub fn set_vec4(&mut self, name: &str, value: Vec4) {
+        self.set_uniform(name, UniformValue::Vec4(value));
+    }
+
+    pub fn set_mat4(&mut self, name: &str, value: Mat4) {
+        self.set_uniform(name, UniformValue::Mat4(value));
+    }
+
+    // Material textures take the texture units from 0 up,
+    // samplers set by hand with `UniformValue::Texture` should use the units above them
+    pub fn set_texture(&mut self, name: &str, texture: Rc<Texture2D>) {
+        self.textures.insert(name.to_string(), texture);
+    }
+
+    pub fn get_texture(&self, name: &str) -> Option<Rc<Texture2D>> {
+        self.textures.get(name).cloned()
+    }
+
+    pub fn remove_texture(&mut self, name: &str) -> Option<Rc<Texture2D>> {
+        self.textures.remove(name)
+    }
+
+    // Uploads every uniform value of this material and binds its textures,
+    // its program has to be bound
+    pub fn apply_uniforms(&self, backend: &dyn RenderBackend) {
+        for (name, value) in self.uniforms.iter() {
+            self.program.set_uniform(backend, name, value);
+        }
+
+        for (unit, (name, texture)) in self.textures.iter().enumerate() {
+            texture.bind(backend, unit as u32);
+            self.program.set_uniform(backend, name, &UniformValue::Texture(unit as u32));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::recording_backend::{RecordingBackend, RenderCommand};
+    use crate::renderer::texture::TextureSettings;
+
+    const VERT_SOURCE: &str = "#version 300 es
+layout(location = 0) in vec4 position;
+void main() {
+    gl_Position = position;
+}";
+
+    const FRAG_SOURCE: &str = "#version 300 es
+precision highp float;
+uniform sampler2D u_albedo;
+uniform sampler2D u_mask;
+out vec4 outColor;
+void main() {
+    outColor = texture(u_albedo, vec2(0.0)) * texture(u_mask, vec2(0.0));
+}";
+
+    #[test]
+    fn samplers_get_their_own_texture_units() {
+        let recording = Rc::new(RecordingBackend::new(640, 480));
+        let backend: Rc<dyn RenderBackend> = recording.clone();
+        let program = Rc::new(ShaderProgram::new(&backend, VERT_SOURCE, FRAG_SOURCE).unwrap());
+        let texture = || Rc::new(Texture2D::from_rgba(&backend, 1, 1, &[255; 4], TextureSettings::new()).unwrap());
+        let albedo = texture();
+        let mask = texture();
+
+        let mut material = Material::new(program.clone());
+        // units follow the sampler names, not the order the textures were set in
+        material.set_texture("u_mask", mask.clone());
+        material.set_texture("u_albedo", albedo.clone());
+
+        recording.take_commands();
+        program.bind(backend.as_ref());
+        material.apply_uniforms(backend.as_ref());
+        let commands = recording.get_commands();
+
+        let expected = [
+            (0, albedo.get_texture(), "u_albedo"),
+            (1, mask.get_texture(), "u_mask"),
+        ];
+        for (unit, texture, name) in expected {
+            assert!(commands.contains(&RenderCommand::BindTexture { unit, texture: Some(texture) }));
+            assert!(commands.contains(&RenderCommand::SetUniform {
+                program: Some(program.get_program()),
+                name: name.to_string(),
+                value: UniformValue::Texture(unit),
+            }));
+        }
+    }
+}
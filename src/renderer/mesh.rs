@@ -0,0 +1,167 @@
+use std::rc::Rc;
+
+use web_sys::WebGl2RenderingContext;
+
+use crate::renderer::render_backend::{BufferHandle, RenderBackend, VertexArrayHandle};
+
+const FLOAT_SIZE: i32 = std::mem::size_of::<f32>() as i32;
+
+pub struct VertexAttribute {
+    pub location: u32,
+    pub size: i32,
+}
+
+// Describes how interleaved vertex data is laid out.
+// Attributes are packed in the order they were added, all of them are floats.
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self {
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with_attribute(mut self, location: u32, size: i32) -> Self {
+        self.attributes.push(VertexAttribute { location, size });
+        self
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn floats_per_vertex(&self) -> i32 {
+        self.attributes.iter().map(|attribute| attribute.size).sum()
+    }
+
+    pub fn stride(&self) -> i32 {
+        self.floats_per_vertex() * FLOAT_SIZE
+    }
+}
+
+struct MeshBuffers {
+    backend: Rc<dyn RenderBackend>,
+    vao: VertexArrayHandle,
+    vertex_buffer: Option<BufferHandle>,
+    index_buffer: Option<BufferHandle>,
+}
+
+impl Drop for MeshBuffers {
+    fn drop(&mut self) {
+        self.backend.delete_vertex_array(self.vao);
+        if let Some(vertex_buffer) = self.vertex_buffer {
+            self.backend.delete_buffer(vertex_buffer);
+        }
+        if let Some(index_buffer) = self.index_buffer {
+            self.backend.delete_buffer(index_buffer);
+        }
+    }
+}
+
+pub struct Mesh {
+    vertices: Vec<f32>,
+    indices: Option<Vec<u32>>,
+    layout: VertexLayout,
+    primitive: u32,
+    buffers: Option<MeshBuffers>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<f32>, indices: Option<Vec<u32>>, layout: VertexLayout) -> Self {
+        Self {
+            vertices,
+            indices,
+            layout,
+            primitive: WebGl2RenderingContext::TRIANGLES,
+            buffers: None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_primitive(&mut self, primitive: u32) {
+        self.primitive = primitive;
+    }
+
+    #[allow(dead_code)]
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn vertex_count(&self) -> i32 {
+        let floats_per_vertex = self.layout.floats_per_vertex();
+        if floats_per_vertex == 0 {
+            return 0;
+        }
+        self.vertices.len() as i32 / floats_per_vertex
+    }
+
+    pub fn is_uploaded(&self) -> bool {
+        self.buffers.is_some()
+    }
+
+    // Creates the GPU buffers and the vertex array object for this mesh.
+    // Uploading again replaces the previous buffers, which are released on drop.
+    pub fn upload(&mut self, backend: &Rc<dyn RenderBackend>) -> Result<(), String> {
+        let vao = backend.create_vertex_array()?;
+        // from here on, drop releases whatever was created if anything fails
+        let mut buffers = MeshBuffers {
+            backend: backend.clone(),
+            vao,
+            vertex_buffer: None,
+            index_buffer: None,
+        };
+
+        backend.bind_vertex_array(Some(vao));
+        let result = self.fill_buffers(backend, &mut buffers);
+        backend.bind_vertex_array(None);
+        result?;
+
+        self.buffers = Some(buffers);
+
+        Ok(())
+    }
+
+    // Uploads the vertex data and sets up the attributes, the VAO has to be bound
+    fn fill_buffers(&self, backend: &Rc<dyn RenderBackend>, buffers: &mut MeshBuffers) -> Result<(), String> {
+        let vertex_buffer = backend.create_buffer()?;
+        buffers.vertex_buffer = Some(vertex_buffer);
+        backend.upload_vertex_buffer(vertex_buffer, &self.vertices);
+
+        let stride = self.layout.stride();
+        let mut offset = 0;
+        for attribute in self.layout.attributes() {
+            backend.set_vertex_attribute(attribute.location, attribute.size, stride, offset);
+            offset += attribute.size * FLOAT_SIZE;
+        }
+
+        // The element array buffer binding is part of the VAO state,
+        // so it has to be bound while the VAO is still active.
+        if let Some(indices) = &self.indices {
+            let index_buffer = backend.create_buffer()?;
+            buffers.index_buffer = Some(index_buffer);
+            backend.upload_index_buffer(index_buffer, indices);
+        }
+
+        Ok(())
+    }
+
+    // `None` until the mesh is uploaded
+    pub fn get_vertex_array(&self) -> Option<VertexArrayHandle> {
+        self.buffers.as_ref().map(|buffers| buffers.vao)
+    }
+
+    // Issues the draw call, the vertex array of the mesh has to be bound
+    pub fn draw(&self, backend: &dyn RenderBackend) {
+        if self.buffers.is_none() {
+            return;
+        }
+
+        match &self.indices {
+            Some(indices) => backend.draw_elements(self.primitive, indices.len() as i32),
+            None => backend.draw_arrays(self.primitive, 0, self.vertex_count()),
+        }
+    }
+}
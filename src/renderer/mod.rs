@@ -1 +1,11 @@
-pub mod gl_render;
\ No newline at end of file
+pub mod gl_render;
+pub mod mesh;
+pub mod shader_program;
+pub mod material;
+pub mod render_queue;
+pub mod state_cache;
+pub mod render_backend;
+pub mod webgl_backend;
+#[cfg(test)]
+pub mod recording_backend;
+pub mod texture;
\ No newline at end of file
@@ -0,0 +1,363 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use web_sys::WebGl2RenderingContext;
+
+use crate::renderer::material::{BlendMode, UniformValue};
+use crate::renderer::render_backend::{
+    ActiveAttribute, ActiveUniform, BufferHandle, ProgramHandle, RenderBackend, TextureHandle, UniformHandle, VertexArrayHandle,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderCommand {
+    CreateVertexArray(VertexArrayHandle),
+    DeleteVertexArray(VertexArrayHandle),
+    BindVertexArray(Option<VertexArrayHandle>),
+    CreateBuffer(BufferHandle),
+    DeleteBuffer(BufferHandle),
+    // number of values uploaded
+    UploadVertexBuffer(BufferHandle, usize),
+    UploadIndexBuffer(BufferHandle, usize),
+    SetVertexAttribute { location: u32, size: i32, stride: i32, offset: i32 },
+    CreateTexture(TextureHandle),
+    DeleteTexture(TextureHandle),
+    UploadTexture { texture: TextureHandle, width: i32, height: i32, internal_format: u32 },
+    SetTextureParameters { texture: TextureHandle, wrap_s: u32, wrap_t: u32, min_filter: u32, mag_filter: u32 },
+    GenerateMipmaps(TextureHandle),
+    BindTexture { unit: u32, texture: Option<TextureHandle> },
+    CreateProgram(ProgramHandle),
+    DeleteProgram(ProgramHandle),
+    UseProgram(Option<ProgramHandle>),
+    SetUniform { program: Option<ProgramHandle>, name: String, value: UniformValue },
+    SetBlendMode(BlendMode),
+    SetDepthTest(bool),
+    SetDepthWrite(bool),
+    SetViewport(i32, i32, i32, i32),
+    SetClearColor(f32, f32, f32, f32),
+    Clear { color: bool, depth: bool },
+    // draws carry the program and vertex array bound at the time
+    DrawArrays { program: Option<ProgramHandle>, vertex_array: Option<VertexArrayHandle>, primitive: u32, first: i32, count: i32 },
+    DrawElements { program: Option<ProgramHandle>, vertex_array: Option<VertexArrayHandle>, primitive: u32, count: i32 },
+}
+
+struct RecordedProgram {
+    attributes: Vec<ActiveAttribute>,
+    uniforms: Vec<ActiveUniform>,
+}
+
+// Logs every call instead of drawing, so a frame can be inspected without a browser or GPU.
+// Programs are "reflected" from the `in` and `uniform` declarations of their sources,
+// which is enough for the shaders of this project, but it's no GLSL compiler.
+pub struct RecordingBackend {
+    commands: RefCell<Vec<RenderCommand>>,
+    next_id: Cell<u32>,
+    drawing_buffer_size: (i32, i32),
+    programs: RefCell<HashMap<u32, RecordedProgram>>,
+    uniform_names: RefCell<HashMap<u32, String>>,
+    program: Cell<Option<ProgramHandle>>,
+    vertex_array: Cell<Option<VertexArrayHandle>>,
+}
+
+impl RecordingBackend {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            commands: RefCell::new(Vec::new()),
+            next_id: Cell::new(1),
+            drawing_buffer_size: (width, height),
+            programs: RefCell::new(HashMap::new()),
+            uniform_names: RefCell::new(HashMap::new()),
+            program: Cell::new(None),
+            vertex_array: Cell::new(None),
+        }
+    }
+
+    pub fn get_commands(&self) -> Vec<RenderCommand> {
+        self.commands.borrow().clone()
+    }
+
+    // Returns the commands recorded so far and starts over, e.g. once per frame
+    pub fn take_commands(&self) -> Vec<RenderCommand> {
+        self.commands.take()
+    }
+
+    pub fn get_draw_calls(&self) -> Vec<RenderCommand> {
+        self.commands
+            .borrow()
+            .iter()
+            .filter(|command| matches!(command, RenderCommand::DrawArrays { .. } | RenderCommand::DrawElements { .. }))
+            .cloned()
+            .collect()
+    }
+
+    fn record(&self, command: RenderCommand) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    fn next_id(&self) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn create_vertex_array(&self) -> Result<VertexArrayHandle, String> {
+        let vertex_array = VertexArrayHandle(self.next_id());
+        self.record(RenderCommand::CreateVertexArray(vertex_array));
+        Ok(vertex_array)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: VertexArrayHandle) {
+        self.record(RenderCommand::DeleteVertexArray(vertex_array));
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<VertexArrayHandle>) {
+        self.vertex_array.set(vertex_array);
+        self.record(RenderCommand::BindVertexArray(vertex_array));
+    }
+
+    fn create_buffer(&self) -> Result<BufferHandle, String> {
+        let buffer = BufferHandle(self.next_id());
+        self.record(RenderCommand::CreateBuffer(buffer));
+        Ok(buffer)
+    }
+
+    fn delete_buffer(&self, buffer: BufferHandle) {
+        self.record(RenderCommand::DeleteBuffer(buffer));
+    }
+
+    fn upload_vertex_buffer(&self, buffer: BufferHandle, data: &[f32]) {
+        self.record(RenderCommand::UploadVertexBuffer(buffer, data.len()));
+    }
+
+    fn upload_index_buffer(&self, buffer: BufferHandle, data: &[u32]) {
+        self.record(RenderCommand::UploadIndexBuffer(buffer, data.len()));
+    }
+
+    fn set_vertex_attribute(&self, location: u32, size: i32, stride: i32, offset: i32) {
+        self.record(RenderCommand::SetVertexAttribute { location, size, stride, offset });
+    }
+
+    fn create_texture(&self) -> Result<TextureHandle, String> {
+        let texture = TextureHandle(self.next_id());
+        self.record(RenderCommand::CreateTexture(texture));
+        Ok(texture)
+    }
+
+    fn delete_texture(&self, texture: TextureHandle) {
+        self.record(RenderCommand::DeleteTexture(texture));
+    }
+
+    fn upload_texture(&self, texture: TextureHandle, width: i32, height: i32, internal_format: u32, pixels: &[u8]) -> Result<(), String> {
+        if pixels.len() != (width * height * 4) as usize {
+            return Err(format!("Expected {} bytes of pixel data, got {}", width * height * 4, pixels.len()));
+        }
+        self.record(RenderCommand::UploadTexture { texture, width, height, internal_format });
+        Ok(())
+    }
+
+    fn set_texture_parameters(&self, texture: TextureHandle, wrap_s: u32, wrap_t: u32, min_filter: u32, mag_filter: u32) {
+        self.record(RenderCommand::SetTextureParameters { texture, wrap_s, wrap_t, min_filter, mag_filter });
+    }
+
+    fn generate_mipmaps(&self, texture: TextureHandle) {
+        self.record(RenderCommand::GenerateMipmaps(texture));
+    }
+
+    fn bind_texture(&self, unit: u32, texture: Option<TextureHandle>) {
+        self.record(RenderCommand::BindTexture { unit, texture });
+    }
+
+    fn create_program(&self, vertex_source: &str, fragment_source: &str) -> Result<ProgramHandle, String> {
+        let program = ProgramHandle(self.next_id());
+
+        let attributes = parse_declarations(vertex_source, "in")
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, gl_type, size, location))| ActiveAttribute {
+                name,
+                location: location.unwrap_or(index as u32),
+                gl_type,
+                size,
+            })
+            .collect();
+
+        let mut uniforms: Vec<ActiveUniform> = Vec::new();
+        for source in [vertex_source, fragment_source] {
+            for (name, gl_type, size, _) in parse_declarations(source, "uniform") {
+                // uniforms shared by both stages are a single uniform of the program
+                if uniforms.iter().any(|uniform| uniform.name == name) {
+                    continue;
+                }
+                let location = UniformHandle(self.next_id());
+                self.uniform_names.borrow_mut().insert(location.0, name.clone());
+                uniforms.push(ActiveUniform { name, location, gl_type, size });
+            }
+        }
+
+        self.programs
+            .borrow_mut()
+            .insert(program.0, RecordedProgram { attributes, uniforms });
+        self.record(RenderCommand::CreateProgram(program));
+        Ok(program)
+    }
+
+    fn delete_program(&self, program: ProgramHandle) {
+        if let Some(recorded) = self.programs.borrow_mut().remove(&program.0) {
+            let mut uniform_names = self.uniform_names.borrow_mut();
+            for uniform in recorded.uniforms {
+                uniform_names.remove(&uniform.location.0);
+            }
+        }
+        self.record(RenderCommand::DeleteProgram(program));
+    }
+
+    fn get_active_attributes(&self, program: ProgramHandle) -> Vec<ActiveAttribute> {
+        self.programs
+            .borrow()
+            .get(&program.0)
+            .map(|recorded| recorded.attributes.clone())
+            .unwrap_or_default()
+    }
+
+    fn get_active_uniforms(&self, program: ProgramHandle) -> Vec<ActiveUniform> {
+        self.programs
+            .borrow()
+            .get(&program.0)
+            .map(|recorded| recorded.uniforms.clone())
+            .unwrap_or_default()
+    }
+
+    fn use_program(&self, program: Option<ProgramHandle>) {
+        self.program.set(program);
+        self.record(RenderCommand::UseProgram(program));
+    }
+
+    fn set_uniform(&self, location: UniformHandle, value: &UniformValue) {
+        let Some(name) = self.uniform_names.borrow().get(&location.0).cloned() else {
+            return;
+        };
+        self.record(RenderCommand::SetUniform {
+            program: self.program.get(),
+            name,
+            value: value.clone(),
+        });
+    }
+
+    fn set_blend_mode(&self, blend_mode: BlendMode) {
+        self.record(RenderCommand::SetBlendMode(blend_mode));
+    }
+
+    fn set_depth_test(&self, enabled: bool) {
+        self.record(RenderCommand::SetDepthTest(enabled));
+    }
+
+    fn set_depth_write(&self, enabled: bool) {
+        self.record(RenderCommand::SetDepthWrite(enabled));
+    }
+
+    fn get_drawing_buffer_size(&self) -> (i32, i32) {
+        self.drawing_buffer_size
+    }
+
+    fn set_viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.record(RenderCommand::SetViewport(x, y, width, height));
+    }
+
+    fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.record(RenderCommand::SetClearColor(r, g, b, a));
+    }
+
+    fn clear(&self, color: bool, depth: bool) {
+        self.record(RenderCommand::Clear { color, depth });
+    }
+
+    fn draw_arrays(&self, primitive: u32, first: i32, count: i32) {
+        self.record(RenderCommand::DrawArrays {
+            program: self.program.get(),
+            vertex_array: self.vertex_array.get(),
+            primitive,
+            first,
+            count,
+        });
+    }
+
+    fn draw_elements(&self, primitive: u32, count: i32) {
+        self.record(RenderCommand::DrawElements {
+            program: self.program.get(),
+            vertex_array: self.vertex_array.get(),
+            primitive,
+            count,
+        });
+    }
+}
+
+// Finds top-level declarations like `layout(location = 0) in vec4 position;` or
+// `uniform highp mat4 u_bones[32];`, returning their name, GL type, array size and explicit location
+fn parse_declarations(source: &str, qualifier: &str) -> Vec<(String, u32, i32, Option<u32>)> {
+    let mut declarations = Vec::new();
+
+    for statement in source.split(';') {
+        // comments end at the line break, the declaration is on the last line of the statement
+        let statement = statement.lines().last().unwrap_or("").trim();
+        let (location, statement) = match statement.strip_prefix("layout") {
+            Some(rest) => {
+                let Some((layout, rest)) = rest.split_once(')') else {
+                    continue;
+                };
+                let location = layout
+                    .split(',')
+                    .filter_map(|entry| entry.split_once('='))
+                    .find(|(key, _)| key.trim_start_matches('(').trim() == "location")
+                    .and_then(|(_, value)| value.trim().parse().ok());
+                (location, rest.trim())
+            }
+            None => (None, statement),
+        };
+
+        let mut words = statement.split_whitespace();
+        if words.next() != Some(qualifier) {
+            continue;
+        }
+        let mut words: Vec<&str> = words
+            .filter(|word| !matches!(*word, "highp" | "mediump" | "lowp" | "flat" | "smooth"))
+            .collect();
+        let Some(declarator) = words.pop() else {
+            continue;
+        };
+        let Some(gl_type) = words.last().and_then(|type_name| gl_type(type_name)) else {
+            continue;
+        };
+
+        let (name, size) = match declarator.split_once('[') {
+            Some((name, size)) => (name, size.trim_end_matches(']').parse().unwrap_or(1)),
+            None => (declarator, 1),
+        };
+        declarations.push((name.to_string(), gl_type, size, location));
+    }
+
+    declarations
+}
+
+fn gl_type(type_name: &str) -> Option<u32> {
+    let gl_type = match type_name {
+        "float" => WebGl2RenderingContext::FLOAT,
+        "vec2" => WebGl2RenderingContext::FLOAT_VEC2,
+        "vec3" => WebGl2RenderingContext::FLOAT_VEC3,
+        "vec4" => WebGl2RenderingContext::FLOAT_VEC4,
+        "int" => WebGl2RenderingContext::INT,
+        "ivec2" => WebGl2RenderingContext::INT_VEC2,
+        "ivec3" => WebGl2RenderingContext::INT_VEC3,
+        "ivec4" => WebGl2RenderingContext::INT_VEC4,
+        "uint" => WebGl2RenderingContext::UNSIGNED_INT,
+        "bool" => WebGl2RenderingContext::BOOL,
+        "mat2" => WebGl2RenderingContext::FLOAT_MAT2,
+        "mat3" => WebGl2RenderingContext::FLOAT_MAT3,
+        "mat4" => WebGl2RenderingContext::FLOAT_MAT4,
+        "sampler2D" => WebGl2RenderingContext::SAMPLER_2D,
+        "sampler3D" => WebGl2RenderingContext::SAMPLER_3D,
+        "samplerCube" => WebGl2RenderingContext::SAMPLER_CUBE,
+        _ => return None,
+    };
+    Some(gl_type)
+}
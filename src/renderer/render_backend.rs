@@ -0,0 +1,86 @@
+use crate::renderer::material::{BlendMode, UniformValue};
+
+// GPU objects are referred to by handles, the backend keeps the actual objects.
+// This way nothing outside of the backend depends on `web_sys` types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BufferHandle(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexArrayHandle(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProgramHandle(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UniformHandle(pub u32);
+
+// An active attribute of a linked program
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveAttribute {
+    pub name: String,
+    pub location: u32,
+    pub gl_type: u32,
+    pub size: i32,
+}
+
+// An active uniform of a linked program, array names are reported without the "[0]" suffix
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveUniform {
+    pub name: String,
+    pub location: UniformHandle,
+    pub gl_type: u32,
+    pub size: i32,
+}
+
+// Every GL call the renderer makes goes through this trait.
+// `WebGlBackend` draws in the browser. `RecordingBackend`, only built for tests,
+// logs the calls instead, so the native tests can check what a frame draws.
+// Primitive and type constants are the usual GL enum values.
+pub trait RenderBackend {
+    fn create_vertex_array(&self) -> Result<VertexArrayHandle, String>;
+    fn delete_vertex_array(&self, vertex_array: VertexArrayHandle);
+    fn bind_vertex_array(&self, vertex_array: Option<VertexArrayHandle>);
+
+    fn create_buffer(&self) -> Result<BufferHandle, String>;
+    fn delete_buffer(&self, buffer: BufferHandle);
+    // Binds the buffer and fills it, index buffers stay bound to the current vertex array
+    fn upload_vertex_buffer(&self, buffer: BufferHandle, data: &[f32]);
+    fn upload_index_buffer(&self, buffer: BufferHandle, data: &[u32]);
+    // Enables a float attribute read from the bound vertex buffer, offsets and stride in bytes
+    fn set_vertex_attribute(&self, location: u32, size: i32, stride: i32, offset: i32);
+
+    fn create_texture(&self) -> Result<TextureHandle, String>;
+    fn delete_texture(&self, texture: TextureHandle);
+    // Fills the base level with tightly packed 8-bit RGBA pixels, stored as `internal_format`
+    fn upload_texture(&self, texture: TextureHandle, width: i32, height: i32, internal_format: u32, pixels: &[u8]) -> Result<(), String>;
+    fn set_texture_parameters(&self, texture: TextureHandle, wrap_s: u32, wrap_t: u32, min_filter: u32, mag_filter: u32);
+    // Builds the mip chain from the base level
+    fn generate_mipmaps(&self, texture: TextureHandle);
+    // Binds the texture to a texture unit, samplers read from the unit they are set to
+    fn bind_texture(&self, unit: u32, texture: Option<TextureHandle>);
+
+    // Compiles, links and reflects the program in one go, the error carries the info log
+    fn create_program(&self, vertex_source: &str, fragment_source: &str) -> Result<ProgramHandle, String>;
+    fn delete_program(&self, program: ProgramHandle);
+    fn get_active_attributes(&self, program: ProgramHandle) -> Vec<ActiveAttribute>;
+    fn get_active_uniforms(&self, program: ProgramHandle) -> Vec<ActiveUniform>;
+    fn use_program(&self, program: Option<ProgramHandle>);
+    // Sets a uniform of the program in use
+    fn set_uniform(&self, location: UniformHandle, value: &UniformValue);
+
+    fn set_blend_mode(&self, blend_mode: BlendMode);
+    fn set_depth_test(&self, enabled: bool);
+    fn set_depth_write(&self, enabled: bool);
+
+    fn get_drawing_buffer_size(&self) -> (i32, i32);
+    fn set_viewport(&self, x: i32, y: i32, width: i32, height: i32);
+    fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32);
+    fn clear(&self, color: bool, depth: bool);
+
+    fn draw_arrays(&self, primitive: u32, first: i32, count: i32);
+    // Indices are read from the index buffer of the bound vertex array, as 32-bit integers
+    fn draw_elements(&self, primitive: u32, count: i32);
+}
@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glm::Mat4;
+
+use crate::renderer::material::Material;
+use crate::renderer::mesh::Mesh;
+
+pub struct DrawItem {
+    pub mesh: Rc<RefCell<Mesh>>,
+    pub material: Rc<RefCell<Material>>,
+    pub model_matrix: Mat4,
+    // Lower layers are drawn first, within both the opaque and the transparent pass
+    pub layer: i32,
+    // Distance from the camera along its view direction
+    pub depth: f32,
+}
+
+// Draw items collected during a frame, split by whether their material blends
+pub struct RenderQueue {
+    opaque: Vec<DrawItem>,
+    transparent: Vec<DrawItem>,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self {
+            opaque: Vec::new(),
+            transparent: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, item: DrawItem) {
+        if item.material.borrow().is_transparent() {
+            self.transparent.push(item);
+        } else {
+            self.opaque.push(item);
+        }
+    }
+
+    // Opaque items are grouped by program and material to save state changes, then drawn
+    // front-to-back so the depth test rejects hidden pixels early.
+    // Transparent items are drawn back-to-front, so blending composes correctly.
+    pub fn sort(&mut self) {
+        self.opaque.sort_by(|a, b| {
+            a.layer
+                .cmp(&b.layer)
+                .then_with(|| program_id(a).cmp(&program_id(b)))
+                .then_with(|| Rc::as_ptr(&a.material).cmp(&Rc::as_ptr(&b.material)))
+                .then_with(|| a.depth.total_cmp(&b.depth))
+        });
+
+        self.transparent
+            .sort_by(|a, b| a.layer.cmp(&b.layer).then_with(|| b.depth.total_cmp(&a.depth)));
+    }
+
+    pub fn get_opaque(&self) -> &[DrawItem] {
+        &self.opaque
+    }
+
+    pub fn get_transparent(&self) -> &[DrawItem] {
+        &self.transparent
+    }
+
+    pub fn clear(&mut self) {
+        self.opaque.clear();
+        self.transparent.clear();
+    }
+}
+
+fn program_id(item: &DrawItem) -> u32 {
+    item.material.borrow().get_program().get_program().0
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::renderer::material::UniformValue;
+use crate::renderer::render_backend::{ProgramHandle, RenderBackend, UniformHandle};
+
+#[allow(dead_code)]
+pub struct AttributeInfo {
+    pub location: u32,
+    pub gl_type: u32,
+    pub size: i32,
+}
+
+#[allow(dead_code)]
+pub struct UniformInfo {
+    pub location: UniformHandle,
+    pub gl_type: u32,
+    pub size: i32,
+}
+
+pub struct ShaderProgram {
+    backend: Rc<dyn RenderBackend>,
+    program: ProgramHandle,
+    attributes: HashMap<String, AttributeInfo>,
+    uniforms: HashMap<String, UniformInfo>,
+}
+
+#[allow(dead_code)]
+impl ShaderProgram {
+    pub fn new(backend: &Rc<dyn RenderBackend>, vert_source: &str, frag_source: &str) -> Result<Self, String> {
+        let program = backend.create_program(vert_source, frag_source)?;
+
+        let mut shader_program = Self {
+            backend: backend.clone(),
+            program,
+            attributes: HashMap::new(),
+            uniforms: HashMap::new(),
+        };
+        shader_program.reflect();
+
+        Ok(shader_program)
+    }
+
+    // Asks the backend for the active attributes and uniforms of the linked program
+    fn reflect(&mut self) {
+        for attribute in self.backend.get_active_attributes(self.program) {
+            self.attributes.insert(attribute.name, AttributeInfo {
+                location: attribute.location,
+                gl_type: attribute.gl_type,
+                size: attribute.size,
+            });
+        }
+
+        for uniform in self.backend.get_active_uniforms(self.program) {
+            self.uniforms.insert(uniform.name, UniformInfo {
+                location: uniform.location,
+                gl_type: uniform.gl_type,
+                size: uniform.size,
+            });
+        }
+    }
+
+    pub fn get_program(&self) -> ProgramHandle {
+        self.program
+    }
+
+    pub fn get_attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes.get(name)
+    }
+
+    pub fn get_uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, AttributeInfo> {
+        &self.attributes
+    }
+
+    pub fn uniforms(&self) -> &HashMap<String, UniformInfo> {
+        &self.uniforms
+    }
+
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.uniforms.contains_key(name)
+    }
+
+    pub fn bind(&self, backend: &dyn RenderBackend) {
+        backend.use_program(Some(self.program));
+    }
+
+    // Sets a uniform on this program. The program has to be bound.
+    // Returns false if the program has no active uniform with that name,
+    // which is not an error, since the compiler strips unused uniforms.
+    pub fn set_uniform(&self, backend: &dyn RenderBackend, name: &str, value: &UniformValue) -> bool {
+        let Some(uniform) = self.uniforms.get(name) else {
+            return false;
+        };
+        backend.set_uniform(uniform.location, value);
+
+        true
+    }
+}
+
+impl Drop for ShaderProgram {
+    fn drop(&mut self) {
+        self.backend.delete_program(self.program);
+    }
+}
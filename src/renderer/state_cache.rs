@@ -0,0 +1,69 @@
+use crate::renderer::material::BlendMode;
+use crate::renderer::render_backend::{ProgramHandle, RenderBackend, VertexArrayHandle};
+
+// Remembers the GL state set through it, so setting the same value again costs nothing.
+// Anything that changes this state behind its back has to be followed by `reset`.
+pub struct StateCache {
+    // `None` means unknown, the next call always reaches the backend
+    program: Option<ProgramHandle>,
+    vertex_array: Option<Option<VertexArrayHandle>>,
+    blend_mode: Option<BlendMode>,
+    depth_test: Option<bool>,
+    depth_write: Option<bool>,
+}
+
+impl StateCache {
+    pub fn new() -> Self {
+        Self {
+            program: None,
+            vertex_array: None,
+            blend_mode: None,
+            depth_test: None,
+            depth_write: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn use_program(&mut self, backend: &dyn RenderBackend, program: ProgramHandle) {
+        if self.program == Some(program) {
+            return;
+        }
+        backend.use_program(Some(program));
+        self.program = Some(program);
+    }
+
+    pub fn bind_vertex_array(&mut self, backend: &dyn RenderBackend, vertex_array: Option<VertexArrayHandle>) {
+        if self.vertex_array == Some(vertex_array) {
+            return;
+        }
+        backend.bind_vertex_array(vertex_array);
+        self.vertex_array = Some(vertex_array);
+    }
+
+    pub fn set_blend_mode(&mut self, backend: &dyn RenderBackend, blend_mode: BlendMode) {
+        if self.blend_mode == Some(blend_mode) {
+            return;
+        }
+        backend.set_blend_mode(blend_mode);
+        self.blend_mode = Some(blend_mode);
+    }
+
+    pub fn set_depth_test(&mut self, backend: &dyn RenderBackend, enabled: bool) {
+        if self.depth_test == Some(enabled) {
+            return;
+        }
+        backend.set_depth_test(enabled);
+        self.depth_test = Some(enabled);
+    }
+
+    pub fn set_depth_write(&mut self, backend: &dyn RenderBackend, enabled: bool) {
+        if self.depth_write == Some(enabled) {
+            return;
+        }
+        backend.set_depth_write(enabled);
+        self.depth_write = Some(enabled);
+    }
+}
@@ -0,0 +1,272 @@
+use std::rc::Rc;
+
+use web_sys::WebGl2RenderingContext;
+
+use crate::renderer::render_backend::{RenderBackend, TextureHandle};
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+    // Blocky, for pixel art and lookup tables
+    Nearest,
+    Linear,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    // Colors as authored in image editors, converted to linear when sampled
+    Srgb,
+    // Data that is already linear, like normal maps or masks
+    Linear,
+}
+
+impl TextureWrap {
+    fn to_gl(self) -> u32 {
+        match self {
+            TextureWrap::Repeat => WebGl2RenderingContext::REPEAT,
+            TextureWrap::MirroredRepeat => WebGl2RenderingContext::MIRRORED_REPEAT,
+            TextureWrap::ClampToEdge => WebGl2RenderingContext::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+impl TextureFormat {
+    fn to_gl(self) -> u32 {
+        match self {
+            TextureFormat::Srgb => WebGl2RenderingContext::SRGB8_ALPHA8,
+            TextureFormat::Linear => WebGl2RenderingContext::RGBA8,
+        }
+    }
+}
+
+// How a texture is stored and sampled, set up before it's created
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureSettings {
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub mipmaps: bool,
+    pub format: TextureFormat,
+}
+
+#[allow(dead_code)]
+impl TextureSettings {
+    // Repeating, trilinear, sRGB, which suits most color textures
+    pub fn new() -> Self {
+        Self {
+            wrap_s: TextureWrap::Repeat,
+            wrap_t: TextureWrap::Repeat,
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Linear,
+            mipmaps: true,
+            format: TextureFormat::Srgb,
+        }
+    }
+
+    pub fn with_wrap(mut self, wrap: TextureWrap) -> Self {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.min_filter = filter;
+        self.mag_filter = filter;
+        self
+    }
+
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A 2D RGBA texture living on the GPU, released on drop
+pub struct Texture2D {
+    backend: Rc<dyn RenderBackend>,
+    texture: TextureHandle,
+    width: u32,
+    height: u32,
+    settings: TextureSettings,
+}
+
+#[allow(dead_code)]
+impl Texture2D {
+    // `pixels` are tightly packed 8-bit RGBA rows, top row first
+    pub fn from_rgba(
+        backend: &Rc<dyn RenderBackend>,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        settings: TextureSettings,
+    ) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid texture size {}x{}", width, height));
+        }
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(format!(
+                "Expected {} bytes of RGBA data for a {}x{} texture, got {}",
+                expected,
+                width,
+                height,
+                pixels.len()
+            ));
+        }
+
+        let texture = backend.create_texture()?;
+        // from here on, drop releases the texture if anything fails
+        let texture = Self {
+            backend: backend.clone(),
+            texture,
+            width,
+            height,
+            settings,
+        };
+        backend.upload_texture(texture.texture, width as i32, height as i32, settings.format.to_gl(), pixels)?;
+        if settings.mipmaps {
+            backend.generate_mipmaps(texture.texture);
+        }
+        texture.apply_sampling();
+
+        Ok(texture)
+    }
+
+    // Decodes a PNG or JPEG file, the format is guessed from its contents
+    pub fn from_image_bytes(backend: &Rc<dyn RenderBackend>, bytes: &[u8], settings: TextureSettings) -> Result<Self, String> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|err| format!("Failed to decode image: {}", err))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+
+        Self::from_rgba(backend, width, height, image.as_raw(), settings)
+    }
+
+    pub fn get_texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_settings(&self) -> &TextureSettings {
+        &self.settings
+    }
+
+    pub fn set_wrap(&mut self, wrap_s: TextureWrap, wrap_t: TextureWrap) {
+        self.settings.wrap_s = wrap_s;
+        self.settings.wrap_t = wrap_t;
+        self.apply_sampling();
+    }
+
+    pub fn set_filter(&mut self, min_filter: TextureFilter, mag_filter: TextureFilter) {
+        self.settings.min_filter = min_filter;
+        self.settings.mag_filter = mag_filter;
+        self.apply_sampling();
+    }
+
+    // Textures created without mipmaps get them built now
+    pub fn generate_mipmaps(&mut self) {
+        self.backend.generate_mipmaps(self.texture);
+        self.settings.mipmaps = true;
+        self.apply_sampling();
+    }
+
+    // Binds the texture to a texture unit, see `UniformValue::Texture`
+    pub fn bind(&self, backend: &dyn RenderBackend, unit: u32) {
+        backend.bind_texture(unit, Some(self.texture));
+    }
+
+    fn apply_sampling(&self) {
+        let settings = &self.settings;
+        // with mipmaps, the min filter also blends between mip levels
+        let min_filter = match (settings.min_filter, settings.mipmaps) {
+            (TextureFilter::Nearest, false) => WebGl2RenderingContext::NEAREST,
+            (TextureFilter::Linear, false) => WebGl2RenderingContext::LINEAR,
+            (TextureFilter::Nearest, true) => WebGl2RenderingContext::NEAREST_MIPMAP_NEAREST,
+            (TextureFilter::Linear, true) => WebGl2RenderingContext::LINEAR_MIPMAP_LINEAR,
+        };
+        let mag_filter = match settings.mag_filter {
+            TextureFilter::Nearest => WebGl2RenderingContext::NEAREST,
+            TextureFilter::Linear => WebGl2RenderingContext::LINEAR,
+        };
+
+        self.backend.set_texture_parameters(
+            self.texture,
+            settings.wrap_s.to_gl(),
+            settings.wrap_t.to_gl(),
+            min_filter,
+            mag_filter,
+        );
+    }
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        self.backend.delete_texture(self.texture);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::recording_backend::{RecordingBackend, RenderCommand};
+
+    fn setup() -> (Rc<RecordingBackend>, Rc<dyn RenderBackend>) {
+        let recording = Rc::new(RecordingBackend::new(640, 480));
+        let backend: Rc<dyn RenderBackend> = recording.clone();
+        (recording, backend)
+    }
+
+    #[test]
+    fn wrong_pixel_length_creates_no_texture() {
+        let (recording, backend) = setup();
+
+        let result = Texture2D::from_rgba(&backend, 2, 2, &[255; 12], TextureSettings::new());
+        assert!(result.is_err());
+        let commands = recording.get_commands();
+        assert!(!commands.iter().any(|command| matches!(command, RenderCommand::CreateTexture(_))));
+    }
+
+    #[test]
+    fn mipmapped_textures_filter_between_mip_levels() {
+        let (recording, backend) = setup();
+
+        let texture = Texture2D::from_rgba(&backend, 2, 2, &[255; 16], TextureSettings::new()).unwrap();
+        let commands = recording.get_commands();
+        assert!(commands.contains(&RenderCommand::GenerateMipmaps(texture.get_texture())));
+        assert!(commands.contains(&RenderCommand::SetTextureParameters {
+            texture: texture.get_texture(),
+            wrap_s: WebGl2RenderingContext::REPEAT,
+            wrap_t: WebGl2RenderingContext::REPEAT,
+            min_filter: WebGl2RenderingContext::LINEAR_MIPMAP_LINEAR,
+            mag_filter: WebGl2RenderingContext::LINEAR,
+        }));
+    }
+}
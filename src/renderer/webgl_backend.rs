@@ -0,0 +1,446 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlShader, WebGlTexture, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::renderer::material::{BlendMode, UniformValue};
+use crate::renderer::render_backend::{
+    ActiveAttribute, ActiveUniform, BufferHandle, ProgramHandle, RenderBackend, TextureHandle, UniformHandle, VertexArrayHandle,
+};
+
+// Draws through a WebGL2 context, GL objects are kept in tables keyed by handle ids
+pub struct WebGlBackend {
+    context: WebGl2RenderingContext,
+    next_id: Cell<u32>,
+    vertex_arrays: RefCell<HashMap<u32, WebGlVertexArrayObject>>,
+    buffers: RefCell<HashMap<u32, WebGlBuffer>>,
+    textures: RefCell<HashMap<u32, WebGlTexture>>,
+    programs: RefCell<HashMap<u32, WebGlProgram>>,
+    uniforms: RefCell<HashMap<u32, WebGlUniformLocation>>,
+    // Uniform handles of each program, released together with it
+    program_uniforms: RefCell<HashMap<u32, Vec<u32>>>,
+}
+
+impl WebGlBackend {
+    pub fn new(context: WebGl2RenderingContext) -> Self {
+        Self {
+            context,
+            next_id: Cell::new(1),
+            vertex_arrays: RefCell::new(HashMap::new()),
+            buffers: RefCell::new(HashMap::new()),
+            textures: RefCell::new(HashMap::new()),
+            programs: RefCell::new(HashMap::new()),
+            uniforms: RefCell::new(HashMap::new()),
+            program_uniforms: RefCell::new(HashMap::new()),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_context(&self) -> &WebGl2RenderingContext {
+        &self.context
+    }
+
+    fn next_id(&self) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    fn get_program(&self, program: ProgramHandle) -> Option<WebGlProgram> {
+        self.programs.borrow().get(&program.0).cloned()
+    }
+
+    // Binds the texture to the active unit so it can be modified
+    fn bind_texture_for_edit(&self, texture: TextureHandle) -> bool {
+        let textures = self.textures.borrow();
+        let Some(texture) = textures.get(&texture.0) else {
+            return false;
+        };
+        self.context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(texture));
+        true
+    }
+}
+
+impl RenderBackend for WebGlBackend {
+    fn create_vertex_array(&self) -> Result<VertexArrayHandle, String> {
+        let vertex_array = self
+            .context
+            .create_vertex_array()
+            .ok_or("Could not create vertex array object")?;
+        let id = self.next_id();
+        self.vertex_arrays.borrow_mut().insert(id, vertex_array);
+        Ok(VertexArrayHandle(id))
+    }
+
+    fn delete_vertex_array(&self, vertex_array: VertexArrayHandle) {
+        if let Some(vertex_array) = self.vertex_arrays.borrow_mut().remove(&vertex_array.0) {
+            self.context.delete_vertex_array(Some(&vertex_array));
+        }
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<VertexArrayHandle>) {
+        let vertex_arrays = self.vertex_arrays.borrow();
+        let vertex_array = vertex_array.and_then(|vertex_array| vertex_arrays.get(&vertex_array.0));
+        self.context.bind_vertex_array(vertex_array);
+    }
+
+    fn create_buffer(&self) -> Result<BufferHandle, String> {
+        let buffer = self.context.create_buffer().ok_or("Failed to create buffer")?;
+        let id = self.next_id();
+        self.buffers.borrow_mut().insert(id, buffer);
+        Ok(BufferHandle(id))
+    }
+
+    fn delete_buffer(&self, buffer: BufferHandle) {
+        if let Some(buffer) = self.buffers.borrow_mut().remove(&buffer.0) {
+            self.context.delete_buffer(Some(&buffer));
+        }
+    }
+
+    fn upload_vertex_buffer(&self, buffer: BufferHandle, data: &[f32]) {
+        let buffers = self.buffers.borrow();
+        self.context
+            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, buffers.get(&buffer.0));
+
+        // Note that `Float32Array::view` is somewhat dangerous (hence the
+        // `unsafe`!). This is creating a raw view into our module's
+        // `WebAssembly.Memory` buffer, but if we allocate more pages for ourself
+        // (aka do a memory allocation in Rust) it'll cause the buffer to change,
+        // causing the `Float32Array` to be invalid.
+        //
+        // As a result, after `Float32Array::view` we have to be very careful not to
+        // do any memory allocations before it's dropped.
+        unsafe {
+            let array_buf_view = js_sys::Float32Array::view(data);
+
+            self.context.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &array_buf_view,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+
+            drop(array_buf_view);
+        }
+    }
+
+    fn upload_index_buffer(&self, buffer: BufferHandle, data: &[u32]) {
+        let buffers = self.buffers.borrow();
+        self.context
+            .bind_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, buffers.get(&buffer.0));
+
+        // Same as above, no allocations while the view is alive.
+        unsafe {
+            let array_buf_view = js_sys::Uint32Array::view(data);
+
+            self.context.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+                &array_buf_view,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+
+            drop(array_buf_view);
+        }
+    }
+
+    fn set_vertex_attribute(&self, location: u32, size: i32, stride: i32, offset: i32) {
+        self.context
+            .vertex_attrib_pointer_with_i32(location, size, WebGl2RenderingContext::FLOAT, false, stride, offset);
+        self.context.enable_vertex_attrib_array(location);
+    }
+
+    fn create_texture(&self) -> Result<TextureHandle, String> {
+        let texture = self.context.create_texture().ok_or("Failed to create texture")?;
+        let id = self.next_id();
+        self.textures.borrow_mut().insert(id, texture);
+        Ok(TextureHandle(id))
+    }
+
+    fn delete_texture(&self, texture: TextureHandle) {
+        if let Some(texture) = self.textures.borrow_mut().remove(&texture.0) {
+            self.context.delete_texture(Some(&texture));
+        }
+    }
+
+    fn upload_texture(&self, texture: TextureHandle, width: i32, height: i32, internal_format: u32, pixels: &[u8]) -> Result<(), String> {
+        if !self.bind_texture_for_edit(texture) {
+            return Err(String::from("Unknown texture"));
+        }
+
+        // rows of RGBA8 are always 4-byte aligned, the default unpack alignment is fine
+        self.context
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                internal_format as i32,
+                width,
+                height,
+                0,
+                WebGl2RenderingContext::RGBA,
+                WebGl2RenderingContext::UNSIGNED_BYTE,
+                Some(pixels),
+            )
+            .map_err(|err| format!("Failed to upload texture: {:?}", err))
+    }
+
+    fn set_texture_parameters(&self, texture: TextureHandle, wrap_s: u32, wrap_t: u32, min_filter: u32, mag_filter: u32) {
+        if !self.bind_texture_for_edit(texture) {
+            return;
+        }
+
+        let context = &self.context;
+        let target = WebGl2RenderingContext::TEXTURE_2D;
+        context.tex_parameteri(target, WebGl2RenderingContext::TEXTURE_WRAP_S, wrap_s as i32);
+        context.tex_parameteri(target, WebGl2RenderingContext::TEXTURE_WRAP_T, wrap_t as i32);
+        context.tex_parameteri(target, WebGl2RenderingContext::TEXTURE_MIN_FILTER, min_filter as i32);
+        context.tex_parameteri(target, WebGl2RenderingContext::TEXTURE_MAG_FILTER, mag_filter as i32);
+    }
+
+    fn generate_mipmaps(&self, texture: TextureHandle) {
+        if self.bind_texture_for_edit(texture) {
+            self.context.generate_mipmap(WebGl2RenderingContext::TEXTURE_2D);
+        }
+    }
+
+    fn bind_texture(&self, unit: u32, texture: Option<TextureHandle>) {
+        let textures = self.textures.borrow();
+        let texture = texture.and_then(|texture| textures.get(&texture.0));
+        self.context.active_texture(WebGl2RenderingContext::TEXTURE0 + unit);
+        self.context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, texture);
+    }
+
+    fn create_program(&self, vertex_source: &str, fragment_source: &str) -> Result<ProgramHandle, String> {
+        let context = &self.context;
+        let vert_shader = compile_shader(context, WebGl2RenderingContext::VERTEX_SHADER, vertex_source)?;
+        let frag_shader = match compile_shader(context, WebGl2RenderingContext::FRAGMENT_SHADER, fragment_source) {
+            Ok(shader) => shader,
+            Err(err) => {
+                context.delete_shader(Some(&vert_shader));
+                return Err(err);
+            }
+        };
+        let program = link_program(context, &vert_shader, &frag_shader);
+
+        // Shaders are not needed anymore once the program is linked (or failed to)
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+
+        let id = self.next_id();
+        self.programs.borrow_mut().insert(id, program?);
+        Ok(ProgramHandle(id))
+    }
+
+    fn delete_program(&self, program: ProgramHandle) {
+        if let Some(uniforms) = self.program_uniforms.borrow_mut().remove(&program.0) {
+            let mut locations = self.uniforms.borrow_mut();
+            for uniform in uniforms {
+                locations.remove(&uniform);
+            }
+        }
+        if let Some(program) = self.programs.borrow_mut().remove(&program.0) {
+            self.context.delete_program(Some(&program));
+        }
+    }
+
+    fn get_active_attributes(&self, program: ProgramHandle) -> Vec<ActiveAttribute> {
+        let context = &self.context;
+        let Some(gl_program) = self.get_program(program) else {
+            return Vec::new();
+        };
+
+        let attribute_count = context
+            .get_program_parameter(&gl_program, WebGl2RenderingContext::ACTIVE_ATTRIBUTES)
+            .as_f64()
+            .unwrap_or(0.0) as u32;
+
+        let mut attributes = Vec::new();
+        for index in 0..attribute_count {
+            let Some(info) = context.get_active_attrib(&gl_program, index) else {
+                continue;
+            };
+            let location = context.get_attrib_location(&gl_program, &info.name());
+            if location < 0 {
+                continue;
+            }
+            attributes.push(ActiveAttribute {
+                name: info.name(),
+                location: location as u32,
+                gl_type: info.type_(),
+                size: info.size(),
+            });
+        }
+        attributes
+    }
+
+    fn get_active_uniforms(&self, program: ProgramHandle) -> Vec<ActiveUniform> {
+        let context = &self.context;
+        let Some(gl_program) = self.get_program(program) else {
+            return Vec::new();
+        };
+
+        let uniform_count = context
+            .get_program_parameter(&gl_program, WebGl2RenderingContext::ACTIVE_UNIFORMS)
+            .as_f64()
+            .unwrap_or(0.0) as u32;
+
+        let mut uniforms = Vec::new();
+        for index in 0..uniform_count {
+            let Some(info) = context.get_active_uniform(&gl_program, index) else {
+                continue;
+            };
+            let Some(location) = context.get_uniform_location(&gl_program, &info.name()) else {
+                continue;
+            };
+
+            let id = self.next_id();
+            self.uniforms.borrow_mut().insert(id, location);
+            self.program_uniforms.borrow_mut().entry(program.0).or_default().push(id);
+
+            // Arrays are reported as "name[0]", but are usually referred to just by "name"
+            let name = info.name();
+            uniforms.push(ActiveUniform {
+                name: name.strip_suffix("[0]").unwrap_or(&name).to_string(),
+                location: UniformHandle(id),
+                gl_type: info.type_(),
+                size: info.size(),
+            });
+        }
+        uniforms
+    }
+
+    fn use_program(&self, program: Option<ProgramHandle>) {
+        let program = program.and_then(|program| self.get_program(program));
+        self.context.use_program(program.as_ref());
+    }
+
+    fn set_uniform(&self, location: UniformHandle, value: &UniformValue) {
+        let uniforms = self.uniforms.borrow();
+        let Some(location) = uniforms.get(&location.0) else {
+            return;
+        };
+        let location = Some(location);
+        let context = &self.context;
+
+        match value {
+            UniformValue::Float(value) => context.uniform1f(location, *value),
+            UniformValue::Int(value) => context.uniform1i(location, *value),
+            UniformValue::Vec2(value) => context.uniform2fv_with_f32_array(location, value.as_slice()),
+            UniformValue::Vec3(value) => context.uniform3fv_with_f32_array(location, value.as_slice()),
+            UniformValue::Vec4(value) => context.uniform4fv_with_f32_array(location, value.as_slice()),
+            UniformValue::Mat4(value) => context.uniform_matrix4fv_with_f32_array(location, false, value.as_slice()),
+            UniformValue::Texture(slot) => context.uniform1i(location, *slot as i32),
+        }
+    }
+
+    fn set_blend_mode(&self, blend_mode: BlendMode) {
+        let context = &self.context;
+        match blend_mode {
+            BlendMode::Opaque => context.disable(WebGl2RenderingContext::BLEND),
+            BlendMode::Alpha => {
+                context.enable(WebGl2RenderingContext::BLEND);
+                context.blend_func(WebGl2RenderingContext::SRC_ALPHA, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA);
+            }
+            BlendMode::Additive => {
+                context.enable(WebGl2RenderingContext::BLEND);
+                context.blend_func(WebGl2RenderingContext::SRC_ALPHA, WebGl2RenderingContext::ONE);
+            }
+        }
+    }
+
+    fn set_depth_test(&self, enabled: bool) {
+        if enabled {
+            self.context.enable(WebGl2RenderingContext::DEPTH_TEST);
+        } else {
+            self.context.disable(WebGl2RenderingContext::DEPTH_TEST);
+        }
+    }
+
+    fn set_depth_write(&self, enabled: bool) {
+        self.context.depth_mask(enabled);
+    }
+
+    fn get_drawing_buffer_size(&self) -> (i32, i32) {
+        (self.context.drawing_buffer_width(), self.context.drawing_buffer_height())
+    }
+
+    fn set_viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.context.viewport(x, y, width, height);
+    }
+
+    fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.context.clear_color(r, g, b, a);
+    }
+
+    fn clear(&self, color: bool, depth: bool) {
+        let mut mask = 0;
+        if color {
+            mask |= WebGl2RenderingContext::COLOR_BUFFER_BIT;
+        }
+        if depth {
+            mask |= WebGl2RenderingContext::DEPTH_BUFFER_BIT;
+        }
+        self.context.clear(mask);
+    }
+
+    fn draw_arrays(&self, primitive: u32, first: i32, count: i32) {
+        self.context.draw_arrays(primitive, first, count);
+    }
+
+    fn draw_elements(&self, primitive: u32, count: i32) {
+        self.context
+            .draw_elements_with_i32(primitive, count, WebGl2RenderingContext::UNSIGNED_INT, 0);
+    }
+}
+
+fn compile_shader(
+    context: &WebGl2RenderingContext,
+    shader_type: u32,
+    source: &str,
+) -> Result<WebGlShader, String> {
+    let shader = context
+        .create_shader(shader_type)
+        .ok_or_else(|| String::from("Unable to create shader object"))?;
+    context.shader_source(&shader, source);
+    context.compile_shader(&shader);
+
+    if context
+        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(shader)
+    } else {
+        let err = context
+            .get_shader_info_log(&shader)
+            .unwrap_or_else(|| String::from("Unknown error creating shader"));
+        context.delete_shader(Some(&shader));
+        Err(err)
+    }
+}
+
+fn link_program(
+    context: &WebGl2RenderingContext,
+    vert_shader: &WebGlShader,
+    frag_shader: &WebGlShader,
+) -> Result<WebGlProgram, String> {
+    let program = context
+        .create_program()
+        .ok_or_else(|| String::from("Unable to create shader object"))?;
+
+    context.attach_shader(&program, vert_shader);
+    context.attach_shader(&program, frag_shader);
+    context.link_program(&program);
+
+    if context
+        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(program)
+    } else {
+        let err = context
+            .get_program_info_log(&program)
+            .unwrap_or_else(|| String::from("Unknown error creating program object"));
+        context.delete_program(Some(&program));
+        Err(err)
+    }
+}
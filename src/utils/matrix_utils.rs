@@ -50,14 +50,11 @@ impl MatrixUtils {
         glm::mat3_to_quat(&glm::Mat3::from_columns(&[c0.xyz(), c1.xyz(), c2.xyz()]))
     }
 
-    pub fn get_rotation(matrix: Mat4) -> Quat {
-        MatrixUtils::get_rotation_with_scale(matrix, Vec3::new(1.0, 1.0, 1.0))
-    }
-
     pub fn decompose_matrix(matrix: Mat4) -> DecomposedMatrix {
         let translation = MatrixUtils::get_translation(matrix);
-        let rotation = MatrixUtils::get_rotation(matrix);
         let scale = MatrixUtils::get_scale(matrix);
+        // the basis columns are scaled, they have to be unit length for a rotation
+        let rotation = MatrixUtils::get_rotation_with_scale(matrix, scale);
 
 
         DecomposedMatrix::new(translation, rotation, scale)